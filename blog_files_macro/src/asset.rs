@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use proc_macro2::Literal;
+use quote::quote;
+use sha2::{Digest, Sha256, Sha384};
+use syn::parse::{Parse, ParseStream};
+use syn::{LitStr, Token};
+
+// hex characters of the content hash spliced into fingerprinted paths
+const FINGERPRINT_LENGTH: usize = 8;
+
+/// `static_asset!("/route.css", "path/from/crate/root.css")`, optionally followed by the
+/// `"sha384-..."` integrity hash the file is pinned to
+pub struct AssetInput {
+    route: LitStr,
+    file: LitStr,
+    pinned: Option<LitStr>,
+}
+
+impl Parse for AssetInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let route = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let file = input.parse()?;
+        let mut pinned = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            pinned = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(AssetInput { route, file, pinned })
+    }
+}
+
+pub fn expand(input: AssetInput) -> syn::Result<proc_macro2::TokenStream> {
+    let route = input.route.value();
+    if !route.starts_with('/') {
+        return Err(syn::Error::new(input.route.span(), "asset routes must start with /"));
+    }
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| syn::Error::new(input.file.span(), e))?;
+    let path = PathBuf::from(manifest_dir).join(input.file.value());
+    let bytes = std::fs::read(&path)
+        .map_err(|e| syn::Error::new(input.file.span(), format!("could not read {}: {}", path.display(), e)))?;
+    let path = path.to_str().ok_or_else(|| syn::Error::new(input.file.span(), "asset path is not utf-8"))?;
+
+    let hash = sha256_hex(&bytes);
+    let etag = format!("\"{}\"", &hash[..32]);
+    let integrity = integrity(&bytes);
+    if let Some(pinned) = &input.pinned
+        && pinned.value() != integrity {
+        return Err(syn::Error::new(input.file.span(), format!("{} is pinned to {}, but its hash is {}", input.file.value(), pinned.value(), integrity)));
+    }
+    let fingerprinted_route = fingerprint(&route, &hash[..FINGERPRINT_LENGTH]);
+    let gzip = quote_variant(&bytes, precompress::gzip(&bytes));
+    let brotli = quote_variant(&bytes, precompress::brotli(&bytes));
+    Ok(quote!(crate::assets::StaticAsset {
+        path: #route,
+        fingerprinted_path: #fingerprinted_route,
+        bytes: include_bytes!(#path),
+        gzip: #gzip,
+        brotli: #brotli,
+        etag: #etag,
+        integrity: #integrity,
+    }))
+}
+
+fn quote_variant(original: &[u8], compressed: Vec<u8>) -> proc_macro2::TokenStream {
+    if precompress::pays_off(original, &compressed) {
+        let compressed = Literal::byte_string(&compressed);
+        quote!(Some(#compressed))
+    } else {
+        quote!(None)
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Subresource integrity value, `sha384-<base64 digest>`
+fn integrity(bytes: &[u8]) -> String {
+    format!("sha384-{}", BASE64_STANDARD.encode(Sha384::digest(bytes)))
+}
+
+/// `/base.css` -> `/base.3f9a1c0d.css`
+fn fingerprint(route: &str, hash: &str) -> String {
+    let file_start = route.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match route[file_start..].find('.') {
+        Some(dot) => format!("{}.{}{}", &route[..file_start + dot], hash, &route[file_start + dot..]),
+        None => format!("{}.{}", route, hash),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint("/base.css", "3f9a1c0d"), "/base.3f9a1c0d.css");
+        assert_eq!(fingerprint("/img/logo.png", "3f9a1c0d"), "/img/logo.3f9a1c0d.png");
+        assert_eq!(fingerprint("/htmx.min.js", "3f9a1c0d"), "/htmx.3f9a1c0d.min.js");
+        assert_eq!(fingerprint("/LICENSE", "3f9a1c0d"), "/LICENSE.3f9a1c0d");
+    }
+
+    #[test]
+    fn test_pinned() {
+        let pinned = |integrity: &str| expand(syn::parse_str(&format!("\"/Cargo.toml\", \"Cargo.toml\", \"{}\"", integrity)).unwrap());
+        let actual = integrity(include_bytes!("../Cargo.toml"));
+        assert!(pinned(&actual).is_ok());
+        assert_eq!(pinned("sha384-abc").unwrap_err().to_string(), format!("Cargo.toml is pinned to sha384-abc, but its hash is {}", actual));
+    }
+
+    #[test]
+    fn test_integrity() {
+        // from the SRI spec's example
+        assert_eq!(integrity(b"alert('Hello, world.');"), "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO");
+    }
+}
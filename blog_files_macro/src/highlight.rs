@@ -0,0 +1,148 @@
+use std::sync::LazyLock;
+
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxSet};
+
+use crate::html_scan::{decode_entities, tokenize, Token};
+use crate::post::escape_html;
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+// TextMate scope prefixes to the Prism token that styles them, most specific first
+const TOKEN_CLASSES: &[(&str, &str)] = &[
+    ("comment", "comment"),
+    ("string.regexp", "regex"),
+    ("constant.character.escape", "char"),
+    ("string", "string"),
+    ("constant.numeric", "number"),
+    ("constant.language", "boolean"),
+    ("constant", "constant"),
+    ("keyword.operator", "operator"),
+    ("keyword", "keyword"),
+    ("storage", "keyword"),
+    ("variable.language", "keyword"),
+    ("support.macro", "function"),
+    ("entity.name.macro", "function"),
+    ("entity.name.function", "function"),
+    ("support.function", "builtin"),
+    ("variable.function", "function"),
+    ("entity.name.tag", "tag"),
+    ("entity.other.attribute-name", "attr-name"),
+    ("entity.name", "class-name"),
+    ("support.type", "class-name"),
+    ("support.class", "class-name"),
+    ("variable.other.member", "property"),
+    // quotes and comment markers take the colour of what they delimit, as in Prism
+    ("punctuation.definition.string", "string"),
+    ("punctuation.definition.comment", "comment"),
+    ("punctuation", "punctuation"),
+];
+
+/// Highlights every `<pre><code class="language-*">` block into Prism-style
+/// `<span class="token keyword">` spans. `<pre>` gets the language class too, which is what the
+/// Prism themes key their block styles on. Blocks in languages we have no grammar for are left alone.
+pub fn highlight_code_blocks(html: &str) -> String {
+    let tokens = tokenize(html);
+    let mut highlighted = String::with_capacity(html.len());
+    let mut copied_to = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let (Token::Start(pre), Some(Token::Start(code))) = (token, tokens.get(i + 1)) else {
+            continue;
+        };
+        if pre.name != "pre" || code.name != "code" || !pre.attributes.trim().is_empty() {
+            continue;
+        }
+        let Some(language) = code.attribute("class").and_then(|c| c.split_whitespace().find_map(|c| c.strip_prefix("language-")).map(str::to_string)) else {
+            continue;
+        };
+        let Some(syntax) = SYNTAXES.find_syntax_by_token(&language) else {
+            continue;
+        };
+        let Some(code_end) = tokens[i + 2..].iter().find_map(|t| match t {
+            Token::End { name, offset } if name == "code" => Some(*offset),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let code_start = code.offset + html[code.offset..].find('>').unwrap() + 1;
+
+        highlighted.push_str(&html[copied_to..pre.offset]);
+        highlighted.push_str(&format!("<pre class=\"language-{}\">", language));
+        highlighted.push_str(&html[code.offset..code_start]);
+        highlighted.push_str(&highlight(&decode_entities(&html[code_start..code_end]), &mut ParseState::new(syntax)));
+        copied_to = code_end;
+    }
+    highlighted.push_str(&html[copied_to..]);
+    highlighted
+}
+
+fn highlight(code: &str, parse_state: &mut ParseState) -> String {
+    let mut highlighted = String::with_capacity(code.len() * 2);
+    let mut scopes = ScopeStack::new();
+    for line in code.split_inclusive('\n') {
+        let Ok(operations) = parse_state.parse_line(line, &SYNTAXES) else {
+            highlighted.push_str(&escape_html(line));
+            continue;
+        };
+        let mut position = 0;
+        for (offset, operation) in operations {
+            push_span(&mut highlighted, &line[position..offset], &scopes);
+            position = offset;
+            // the stack only fails on grammars popping more than they pushed, nothing to style then
+            let _ = scopes.apply(&operation);
+        }
+        push_span(&mut highlighted, &line[position..], &scopes);
+    }
+    highlighted
+}
+
+fn push_span(out: &mut String, text: &str, scopes: &ScopeStack) {
+    if text.is_empty() {
+        return;
+    }
+    match token_class(scopes.as_slice()) {
+        Some(class) if !text.trim().is_empty() => {
+            out.push_str(&format!("<span class=\"token {}\">{}</span>", class, escape_html(text)));
+        }
+        _ => out.push_str(&escape_html(text)),
+    }
+}
+
+/// The Prism token for the innermost scope that has one
+fn token_class(scopes: &[Scope]) -> Option<&'static str> {
+    scopes.iter().rev().find_map(|scope| {
+        let scope = scope.build_string();
+        TOKEN_CLASSES.iter()
+            .find(|(prefix, _)| scope == *prefix || scope.starts_with(&format!("{}.", prefix)))
+            .map(|(_, class)| *class)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_highlight_code_blocks() {
+        let html = highlight_code_blocks("<p>x</p><pre><code class=\"language-rust\">fn main() { let s = \"&lt;b&gt;\"; }</code></pre><p>y</p>");
+        assert!(html.starts_with("<p>x</p><pre class=\"language-rust\"><code class=\"language-rust\">"));
+        assert!(html.contains("<span class=\"token keyword\">fn</span>"));
+        assert!(html.contains("<span class=\"token function\">main</span>"));
+        assert!(html.contains("<span class=\"token string\">&quot;</span><span class=\"token string\">&lt;b&gt;</span>"));
+        assert!(html.ends_with("</code></pre><p>y</p>"));
+
+        let python = highlight_code_blocks("<pre><code class=\"language-python\">if n <= 1: # base\n    return n</code></pre>");
+        assert!(python.contains("<span class=\"token operator\">&lt;=</span>"));
+        assert!(python.contains("<span class=\"token comment\">"));
+
+        // numeric references are decoded before highlighting, so they aren't escaped a second time
+        let numeric = highlight_code_blocks("<pre><code class=\"language-python\">print(&#39;a&#x27;)</code></pre>");
+        assert!(numeric.contains("<span class=\"token string\">'</span><span class=\"token string\">a</span>"), "{}", numeric);
+        assert!(!numeric.contains("&amp;"));
+    }
+
+    #[test]
+    fn test_unknown_language_untouched() {
+        let html = "<pre><code class=\"language-nope\">a &lt; b</code></pre><pre><code>plain</code></pre>";
+        assert_eq!(highlight_code_blocks(html), html);
+    }
+}
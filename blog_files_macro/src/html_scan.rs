@@ -0,0 +1,202 @@
+/// Just enough of an html tokenizer to pull metadata out of hand written posts.
+/// Tags open with `<` followed by a letter, so things like `n <= 1` inside code blocks stay text.
+#[derive(Debug, PartialEq)]
+pub enum Token<'a> {
+    Start(Tag<'a>),
+    End { name: String, offset: usize },
+    Text(&'a str),
+    Comment,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Tag<'a> {
+    pub name: String,
+    pub attributes: &'a str,
+    pub self_closing: bool,
+    pub offset: usize,
+}
+
+impl Tag<'_> {
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        parse_attributes(self.attributes).into_iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.attribute("class").is_some_and(|c| c.split_whitespace().any(|c| c == class))
+    }
+}
+
+pub fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let bytes = html.as_bytes();
+    let mut tokens = vec![];
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'<' {
+            i += 1;
+            continue;
+        }
+        let rest = &html[i..];
+        let tag_end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|e| (e + 3, Token::Comment))
+        } else if rest.starts_with("</") && rest[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            rest.find('>').map(|e| (e + 1, Token::End { name: tag_name(&rest[2..e]), offset: i }))
+        } else if rest.starts_with("<!") {
+            rest.find('>').map(|e| (e + 1, Token::Comment))
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            rest.find('>').map(|e| {
+                let inner = &rest[1..e];
+                let name = tag_name(inner);
+                let attributes = inner[name.len()..].trim_end_matches('/');
+                (e + 1, Token::Start(Tag { name, attributes, self_closing: inner.ends_with('/'), offset: i }))
+            })
+        } else {
+            None
+        };
+        match tag_end {
+            Some((len, token)) => {
+                if text_start < i {
+                    tokens.push(Token::Text(&html[text_start..i]));
+                }
+                tokens.push(token);
+                i += len;
+                text_start = i;
+            }
+            None => i += 1,
+        }
+    }
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+fn tag_name(inner: &str) -> String {
+    inner.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn parse_attributes(attributes: &str) -> Vec<(String, String)> {
+    let mut parsed = vec![];
+    let mut rest = attributes.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+        let name = rest[..name_end].to_string();
+        rest = rest[name_end..].trim_start();
+        let value = if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (value, remaining) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after_eq[1..].find(quote).map(|e| e + 1).unwrap_or(after_eq.len());
+                    (&after_eq[1..end], after_eq.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..end], &after_eq[end..])
+                }
+            };
+            rest = remaining.trim_start();
+            value.to_string()
+        } else {
+            String::new()
+        };
+        if name.is_empty() {
+            break;
+        }
+        parsed.push((name, value));
+    }
+    parsed
+}
+
+const INLINE_ELEMENTS: &[&str] = &["a", "abbr", "b", "code", "em", "i", "kbd", "mark", "s", "small", "span", "strong", "sub", "sup", "u"];
+
+/// Plain text of an html fragment with whitespace collapsed and common entities decoded
+pub fn text_content(html: &str) -> String {
+    let text = tokenize(html).into_iter()
+        .map(|t| match t {
+            Token::Text(t) => t,
+            Token::Start(Tag { name, .. }) | Token::End { name, .. } if INLINE_ELEMENTS.contains(&name.as_str()) => "",
+            _ => " ",
+        })
+        .collect::<String>();
+    decode_entities(&text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Decodes the named references markdown and hand written posts use, and numeric ones like `&#39;`
+/// and `&#x27;`. Anything else is left as it is.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]).and_then(|name| Some((name, decode_entity(name)?)));
+        match entity {
+            Some((name, c)) => {
+                decoded.push(c);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded + rest
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "nbsp" => Some(' '),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "amp" => Some('&'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("<p class=\"post-metadata\">a <= b</p><br/>");
+        assert_eq!(tokens.len(), 4);
+        match &tokens[0] {
+            Token::Start(tag) => {
+                assert_eq!(tag.name, "p");
+                assert!(tag.has_class("post-metadata"));
+            }
+            t => panic!("unexpected token {:?}", t),
+        }
+        assert_eq!(tokens[1], Token::Text("a <= b"));
+        assert_eq!(tokens[2], Token::End { name: "p".to_string(), offset: 31 });
+        assert!(matches!(&tokens[3], Token::Start(Tag { self_closing: true, .. })));
+    }
+
+    #[test]
+    fn test_text_content() {
+        assert_eq!(text_content("<p>\n  Hello <u><a href='x'>b</a>ig</u>\n world &amp; co</p><p>next</p>"), "Hello big world & co next");
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("a &lt;b&gt; &amp;lt; &quot;c&quot;&nbsp;"), "a <b> &lt; \"c\" ");
+        assert_eq!(decode_entities("it&#39;s &#x27;x&#X27; &#8212; &apos;"), "it's 'x' \u{2014} '");
+        assert_eq!(decode_entities("a & b &unknown; &#xzz; &#1114112; &"), "a & b &unknown; &#xzz; &#1114112; &");
+    }
+}
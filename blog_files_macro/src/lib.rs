@@ -3,14 +3,87 @@ use std::path::PathBuf;
 
 use glob::glob;
 use quote::quote;
+use syn::parse_macro_input;
 
+use crate::asset::AssetInput;
+use crate::post::Post;
+
+mod asset;
+mod highlight;
+mod html_scan;
+mod post;
+mod validate;
+
+/// Expands to a `&'static [BlogPost]` for every `*.html` and `*.md` file in `klamer_dev/blog`, newest first.
+/// Markdown posts are rendered to html, code blocks are syntax highlighted and all the metadata is
+/// worked out here, at compile time.
+/// Posts are validated along the way: a missing title or date, duplicate slugs, unbalanced tags
+/// or links to posts that don't exist fail the build with an error naming the file.
 #[proc_macro]
 pub fn list_blog_files(_: TokenStream) -> TokenStream {
-    let files = glob(&(PathBuf::from("./klamer_dev/blog").canonicalize().unwrap().into_os_string().into_string().unwrap() + "/*.html")).unwrap();
-    let file_literals = files.map(|file| {
-        let file = file.unwrap().canonicalize().unwrap().into_os_string().into_string().unwrap();
-        quote!(#file)
+    let blog_dir = PathBuf::from("./klamer_dev/blog").canonicalize().unwrap().into_os_string().into_string().unwrap();
+    let mut files = vec![];
+    for extension in ["html", "md"] {
+        files.extend(glob(&format!("{}/*.{}", blog_dir, extension)).unwrap()
+            .map(|file| file.unwrap().canonicalize().unwrap().into_os_string().into_string().unwrap()));
+    }
+    files.sort();
+
+    let mut errors = vec![];
+    let mut posts = files.iter()
+        .filter_map(|file| Post::load(file).map_err(|e| errors.push(e)).ok())
+        .collect::<Vec<_>>();
+    errors.extend(validate::validate(&posts));
+    if !errors.is_empty() {
+        return quote!({
+            #(compile_error!(#errors);)*
+            &[]
+        }).into();
+    }
+    posts.sort_by(|a, b| b.publish_date().cmp(&a.publish_date()).then_with(|| a.slug().cmp(b.slug())));
+
+    let posts = posts.iter().map(|post| {
+        let path = &post.path;
+        let content = highlight::highlight_code_blocks(&post.body);
+        // still include the source so cargo rebuilds when the post changes
+        let content = quote!({
+            const _: &str = include_str!(#path);
+            #content
+        });
+        let slug = post.slug();
+        let title = post.title.as_ref().expect("validated");
+        let (year, month, day) = post.publish_date().expect("validated");
+        let summary = post.summary();
+        let word_count = post.word_count();
+        let tags = &post.tags;
+        let content_hash = &post.content_hash;
+        let draft = post.draft;
+        quote!(crate::blog::BlogPost {
+            slug: #slug,
+            title: #title,
+            published: crate::blog::PostDate { year: #year, month: #month, day: #day },
+            summary: #summary,
+            word_count: #word_count,
+            tags: &[#(#tags),*],
+            content_hash: #content_hash,
+            draft: #draft,
+            content: #content,
+        })
     });
 
-    quote!([#((#file_literals, include_str!(#file_literals))),*]).into()
-}
\ No newline at end of file
+    quote!(&[#(#posts),*]).into()
+}
+
+/// Embeds a file (path relative to the calling crate's root) as a `StaticAsset` served at the given
+/// route. The ETag and fingerprinted route both come from the file's sha256, the SRI hash from its
+/// sha384, and gzip and brotli variants are embedded next to the original when they're meaningfully
+/// smaller, all at compile time.
+///
+/// `static_asset!("/base.css", "css/base.css")`, or with the integrity hash a downloaded file is
+/// pinned to as a third argument, which fails the build when the file is anything else
+#[proc_macro]
+pub fn static_asset(input: TokenStream) -> TokenStream {
+    asset::expand(parse_macro_input!(input as AssetInput))
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
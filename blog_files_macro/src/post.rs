@@ -0,0 +1,356 @@
+use std::path::Path;
+
+use pulldown_cmark::{html, Options, Parser};
+use serde::Deserialize;
+
+use crate::asset::sha256_hex;
+use crate::html_scan::{text_content, tokenize, Tag, Token};
+
+const SUMMARY_LENGTH: usize = 200;
+const BLOCK_ELEMENTS: &[&str] = &["p", "ol", "ul", "pre", "div", "h1", "h2", "h3", "table", "blockquote"];
+
+/// Metadata block at the top of a markdown post, either YAML between `---` fences
+/// or TOML between `+++` fences.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub date: Option<FrontMatterDate>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub draft: bool,
+}
+
+// TOML has a native date type, YAML dates come through as plain strings
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum FrontMatterDate {
+    Text(String),
+    Toml(toml::value::Datetime),
+}
+
+impl std::fmt::Display for FrontMatterDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrontMatterDate::Text(s) => write!(f, "{}", s),
+            FrontMatterDate::Toml(d) => write!(f, "{}", d),
+        }
+    }
+}
+
+pub enum PostFormat {
+    Html,
+    Markdown,
+}
+
+pub struct Post {
+    pub path: String,
+    pub format: PostFormat,
+    pub body: String,
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub draft: bool,
+    /// Hex sha256 of the source file, front matter included
+    pub content_hash: String,
+}
+
+impl Post {
+    pub fn load(path: &str) -> Result<Post, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: could not read post: {}", path, e))?;
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("md") => Self::from_markdown(path, &source),
+            _ => Ok(Self::from_html(path, source)),
+        }
+    }
+
+    fn from_html(path: &str, source: String) -> Post {
+        let title = extract_h1(&source);
+        let date = extract_post_metadata(&source);
+        let content_hash = sha256_hex(source.as_bytes());
+        Post {
+            path: path.to_string(),
+            format: PostFormat::Html,
+            body: source,
+            title,
+            date,
+            description: None,
+            tags: vec![],
+            draft: false,
+            content_hash,
+        }
+    }
+
+    fn from_markdown(path: &str, source: &str) -> Result<Post, String> {
+        let (front_matter, markdown) = split_front_matter(source).map_err(|e| format!("{}: {}", path, e))?;
+        let rendered = render_markdown(markdown);
+        let title = front_matter.title.clone().or_else(|| extract_h1(&rendered));
+        let date = front_matter.date.as_ref().map(|d| d.to_string());
+
+        // give markdown posts the same heading block the hand written html posts start with
+        let mut body = String::new();
+        let mut rendered = rendered.as_str();
+        if let Some(title) = &front_matter.title {
+            body += &format!("<h1>{}</h1>\n", escape_html(title));
+            // the front matter title replaces a `# Heading` the markdown opens with
+            rendered = strip_leading_h1(rendered);
+        }
+        if let Some(date) = &date {
+            body += &format!("<p class=\"post-metadata\">{}</p>\n", escape_html(date));
+        }
+        body += rendered;
+
+        Ok(Post {
+            path: path.to_string(),
+            format: PostFormat::Markdown,
+            body,
+            title,
+            date,
+            description: front_matter.description,
+            tags: front_matter.tags,
+            draft: front_matter.draft,
+            content_hash: sha256_hex(source.as_bytes()),
+        })
+    }
+
+    pub fn slug(&self) -> &str {
+        slug(&self.path)
+    }
+
+    /// Path relative to the workspace, for error messages
+    pub fn display_path(&self) -> &str {
+        std::env::current_dir().ok()
+            .and_then(|dir| self.path.strip_prefix(dir.to_str()?))
+            .map(|p| p.trim_start_matches('/'))
+            .unwrap_or(&self.path)
+    }
+
+    pub fn publish_date(&self) -> Result<(u16, u8, u8), String> {
+        let date = self.date.as_deref().ok_or_else(|| match self.format {
+            PostFormat::Html => "missing publish date, add a <p class=\"post-metadata\">YYYY-MM-DD</p> line".to_string(),
+            PostFormat::Markdown => "missing publish date, add `date` to the front matter".to_string(),
+        })?;
+        parse_date(date)
+    }
+
+    /// 1 based line of a byte offset into the body, only meaningful for html posts where the body is the source
+    pub fn line_of(&self, offset: usize) -> Option<usize> {
+        match self.format {
+            PostFormat::Html => Some(self.body[..offset].matches('\n').count() + 1),
+            PostFormat::Markdown => None,
+        }
+    }
+
+    /// The declared description, otherwise the start of the first real paragraph
+    pub fn summary(&self) -> String {
+        self.description.clone().unwrap_or_else(|| first_paragraph(&self.body).map(|p| truncate_words(&p, SUMMARY_LENGTH)).unwrap_or_default())
+    }
+
+    pub fn word_count(&self) -> usize {
+        text_content(&self.body).split_whitespace().count()
+    }
+}
+
+pub fn split_front_matter(source: &str) -> Result<(FrontMatter, &str), String> {
+    let source = source.trim_start_matches('\u{feff}');
+    for (fence, is_toml) in [("---", false), ("+++", true)] {
+        let Some(rest) = source.strip_prefix(fence).and_then(|r| r.strip_prefix('\n').or_else(|| r.strip_prefix("\r\n"))) else {
+            continue;
+        };
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == fence {
+                let block = &rest[..offset];
+                let front_matter = if is_toml {
+                    toml::from_str(block).map_err(|e| format!("invalid TOML front matter: {}", e))?
+                } else if block.trim().is_empty() {
+                    FrontMatter::default()
+                } else {
+                    serde_yaml::from_str(block).map_err(|e| format!("invalid YAML front matter: {}", e))?
+                };
+                return Ok((front_matter, &rest[offset + line.len()..]));
+            }
+            offset += line.len();
+        }
+        return Err(format!("front matter opened with {} but never closed", fence));
+    }
+    Ok((FrontMatter::default(), source))
+}
+
+pub fn render_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_SMART_PUNCTUATION;
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(markdown, options));
+    rendered
+}
+
+/// Everything after the first `<h1>`, if the html starts with one
+fn strip_leading_h1(html: &str) -> &str {
+    let trimmed = html.trim_start();
+    if !trimmed.starts_with("<h1>") && !trimmed.starts_with("<h1 ") {
+        return html;
+    }
+    trimmed.find("</h1>").map_or(html, |end| trimmed[end + "</h1>".len()..].trim_start())
+}
+
+pub fn slug(path: &str) -> &str {
+    Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or(path)
+}
+
+/// Text of the first `<h1>`
+pub fn extract_h1(html: &str) -> Option<String> {
+    element_text(html, |tag| tag.name == "h1")
+}
+
+/// Text of the `<p class="post-metadata">` line
+pub fn extract_post_metadata(html: &str) -> Option<String> {
+    element_text(html, |tag| tag.name == "p" && tag.has_class("post-metadata"))
+}
+
+fn element_text(html: &str, matches: impl Fn(&Tag) -> bool) -> Option<String> {
+    let tokens = tokenize(html);
+    let start = tokens.iter().position(|t| matches!(t, Token::Start(tag) if matches(tag)))?;
+    let Token::Start(tag) = &tokens[start] else { unreachable!() };
+    let end = tokens[start..].iter().position(|t| matches!(t, Token::End { name, .. } if *name == tag.name))? + start;
+    let text = tokens[start + 1..end].iter()
+        .filter_map(|t| match t {
+            Token::Text(t) => Some(*t),
+            _ => None,
+        })
+        .collect::<String>();
+    Some(text_content(&text)).filter(|t| !t.is_empty())
+}
+
+/// `YYYY-MM-DD`, anything after the day (like a TOML time) is ignored
+pub fn parse_date(date: &str) -> Result<(u16, u8, u8), String> {
+    let invalid = || format!("invalid date `{}`, expected YYYY-MM-DD", date);
+    let mut parts = date.get(..10).ok_or_else(invalid)?.split('-');
+    let year = parts.next().and_then(|y| y.parse::<u16>().ok()).ok_or_else(invalid)?;
+    let month = parts.next().and_then(|m| m.parse::<u8>().ok()).filter(|m| (1..=12).contains(m)).ok_or_else(invalid)?;
+    let day = parts.next().and_then(|d| d.parse::<u8>().ok()).filter(|d| (1..=31).contains(d)).ok_or_else(invalid)?;
+    Ok((year, month, day))
+}
+
+/// Plain text of the first `<p>` that isn't the `post-metadata` line
+fn first_paragraph(html: &str) -> Option<String> {
+    let mut in_paragraph = false;
+    let mut paragraph = String::new();
+    for token in tokenize(html) {
+        match token {
+            Token::Start(tag) if !in_paragraph => in_paragraph = tag.name == "p" && !tag.has_class("post-metadata"),
+            // a nested block element ends the paragraph just like </p> does
+            Token::Start(tag) if BLOCK_ELEMENTS.contains(&tag.name.as_str()) => in_paragraph = false,
+            Token::End { name, .. } if name == "p" => in_paragraph = false,
+            Token::Text(t) if in_paragraph => paragraph += t,
+            _ => {}
+        }
+        if !in_paragraph && !paragraph.trim().is_empty() {
+            return Some(text_content(&paragraph));
+        }
+    }
+    None
+}
+
+fn truncate_words(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    for word in text.split_whitespace() {
+        if truncated.len() + word.len() + 1 > max_len {
+            break;
+        }
+        if !truncated.is_empty() {
+            truncated.push(' ');
+        }
+        truncated += word;
+    }
+    truncated.trim_end_matches(|c: char| c.is_ascii_punctuation()).to_string() + "…"
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let (front_matter, body) = split_front_matter("---\ntitle: Hello\ndate: 2024-02-03\ntags: [rust, web]\n---\n# Body\n").unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(front_matter.date.unwrap().to_string(), "2024-02-03");
+        assert_eq!(front_matter.tags, vec!["rust", "web"]);
+        assert!(!front_matter.draft);
+        assert_eq!(body, "# Body\n");
+    }
+
+    #[test]
+    fn test_toml_front_matter() {
+        let (front_matter, body) = split_front_matter("+++\ntitle = \"Hello\"\ndate = 2024-02-03\ndraft = true\n+++\nBody").unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(front_matter.date.unwrap().to_string(), "2024-02-03");
+        assert!(front_matter.draft);
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn test_no_front_matter() {
+        let (front_matter, body) = split_front_matter("# Just markdown").unwrap();
+        assert_eq!(front_matter, FrontMatter::default());
+        assert_eq!(body, "# Just markdown");
+        assert!(split_front_matter("---\ntitle: never closed\n").is_err());
+    }
+
+    #[test]
+    fn test_front_matter_title_replaces_h1() {
+        let post = Post::from_markdown("blog/post.md", "---\ntitle: Hello\ndate: 2024-02-03\n---\n# Hello again\n\nBody\n").unwrap();
+        assert_eq!(post.body.matches("<h1>").count(), 1);
+        assert!(post.body.starts_with("<h1>Hello</h1>\n<p class=\"post-metadata\">2024-02-03</p>\n<p>Body</p>"));
+
+        let post = Post::from_markdown("blog/post.md", "---\ndate: 2024-02-03\n---\n# From markdown\n").unwrap();
+        assert_eq!(post.title.as_deref(), Some("From markdown"));
+        assert!(post.body.contains("<h1>From markdown</h1>"));
+        assert_eq!(strip_leading_h1("<p>a</p><h1>b</h1>"), "<p>a</p><h1>b</h1>");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2024-01-31"), Ok((2024, 1, 31)));
+        assert_eq!(parse_date("2024-01-31T10:00:00Z"), Ok((2024, 1, 31)));
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("Jan 1st").is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let post = Post::from_html("blog/post.html", "<h1>T</h1><p class=\"post-metadata\">2024-01-01</p><p>\n First <a href=\"/\">para</a>.</p><p>Second</p>".to_string());
+        assert_eq!(post.summary(), "First para.");
+        assert_eq!(post.word_count(), 5);
+        assert_eq!(truncate_words("one two three four", 12), "one two…");
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("/somebullshit/postname"), "postname");
+        assert_eq!(slug("/somebullshit/postname.html"), "postname");
+        assert_eq!(slug("somebullshit/postname"), "postname");
+        assert_eq!(slug("somebullshit/postname.html"), "postname");
+        assert_eq!(slug("somebullshit/postname.md"), "postname");
+        assert_eq!(slug("a/b/c/d/e/r/postname"), "postname");
+        assert_eq!(slug("a/b/c/d/e/r/posthtml"), "posthtml");
+    }
+
+    #[test]
+    fn test_extract() {
+        let html = "<h1>Hello <em>there</em></h1>\n<p class=\"intro post-metadata\">\n 2024-01-01 </p>";
+        assert_eq!(extract_h1(html).as_deref(), Some("Hello there"));
+        assert_eq!(extract_post_metadata(html).as_deref(), Some("2024-01-01"));
+        assert_eq!(extract_h1("<h2>Nope</h2>"), None);
+    }
+}
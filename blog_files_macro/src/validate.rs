@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::html_scan::{tokenize, Token};
+use crate::post::Post;
+
+// routes under /blog/ that aren't posts
+const RESERVED_SLUGS: &[&str] = &["archive"];
+// elements that never have an end tag
+const VOID_ELEMENTS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+// elements whose end tag html lets you leave off
+const OPTIONAL_END_ELEMENTS: &[&str] = &["p", "li", "dt", "dd", "option", "optgroup", "tr", "td", "th", "thead", "tbody", "tfoot", "colgroup", "rp", "rt"];
+
+/// Every problem found across the posts, each naming the file it came from
+pub fn validate(posts: &[Post]) -> Vec<String> {
+    let mut errors = vec![];
+    let mut slugs: HashMap<&str, &Post> = HashMap::new();
+    for post in posts {
+        if RESERVED_SLUGS.contains(&post.slug()) {
+            errors.push(format!("{}: `{}` is reserved for /blog/{}, rename the post", post.display_path(), post.slug(), post.slug()));
+        }
+        if let Some(other) = slugs.insert(post.slug(), post) {
+            errors.push(format!("{}: duplicate slug `{}`, also used by {}", post.display_path(), post.slug(), other.display_path()));
+        }
+    }
+
+    for post in posts {
+        let mut error = |offset: Option<usize>, message: String| {
+            match offset.and_then(|o| post.line_of(o)) {
+                Some(line) => errors.push(format!("{}:{}: {}", post.display_path(), line, message)),
+                None => errors.push(format!("{}: {}", post.display_path(), message)),
+            }
+        };
+        if post.title.is_none() {
+            error(None, "missing title, add an <h1> (or `title` to the front matter)".to_string());
+        }
+        if let Err(e) = post.publish_date() {
+            error(None, e);
+        }
+        for (offset, message) in check_balanced(&post.body) {
+            error(Some(offset), message);
+        }
+        for (offset, href) in internal_links(&post.body) {
+            let slug = href.trim_start_matches("/blog/").split(['/', '#', '?']).next().unwrap_or_default();
+            if !slugs.contains_key(slug) && !RESERVED_SLUGS.contains(&slug) {
+                error(Some(offset), format!("broken link to `{}`, there is no post `{}`", href, slug));
+            }
+        }
+    }
+    errors
+}
+
+/// Unclosed and stray tags, as (byte offset, message)
+fn check_balanced(html: &str) -> Vec<(usize, String)> {
+    let mut problems = vec![];
+    let mut open: Vec<(String, usize)> = vec![];
+    for token in tokenize(html) {
+        match token {
+            Token::Start(tag) if tag.self_closing || VOID_ELEMENTS.contains(&tag.name.as_str()) => {}
+            Token::Start(tag) => open.push((tag.name, tag.offset)),
+            Token::End { name, offset } if VOID_ELEMENTS.contains(&name.as_str()) => {
+                problems.push((offset, format!("</{}> closes a void element, drop the end tag", name)));
+            }
+            Token::End { name, offset } => match open.iter().rposition(|(n, _)| *n == name) {
+                None => problems.push((offset, format!("</{}> has no matching <{}>", name, name))),
+                Some(position) => {
+                    for (unclosed, unclosed_offset) in open.drain(position..).skip(1) {
+                        if !OPTIONAL_END_ELEMENTS.contains(&unclosed.as_str()) {
+                            problems.push((unclosed_offset, format!("<{}> is never closed before </{}>", unclosed, name)));
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    for (unclosed, offset) in open {
+        if !OPTIONAL_END_ELEMENTS.contains(&unclosed.as_str()) {
+            problems.push((offset, format!("<{}> is never closed", unclosed)));
+        }
+    }
+    problems
+}
+
+/// `href`s pointing at other posts, as (byte offset, href)
+fn internal_links(html: &str) -> Vec<(usize, String)> {
+    tokenize(html).into_iter()
+        .filter_map(|token| match token {
+            Token::Start(tag) if tag.name == "a" => tag.attribute("href")
+                .filter(|href| href.starts_with("/blog/"))
+                .map(|href| (tag.offset, href)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_balanced() {
+        assert!(check_balanced("<div><p>one<p>two</div><ol><li>a<li>b</ol><br><img src=\"x\"/>").is_empty());
+        assert_eq!(check_balanced("<div><span>x</div>"), vec![(5, "<span> is never closed before </div>".to_string())]);
+        assert_eq!(check_balanced("<div>x</em></div>"), vec![(6, "</em> has no matching <em>".to_string())]);
+        assert_eq!(check_balanced("<section>"), vec![(0, "<section> is never closed".to_string())]);
+        assert_eq!(check_balanced("<hr></hr>"), vec![(4, "</hr> closes a void element, drop the end tag".to_string())]);
+    }
+
+    #[test]
+    fn test_internal_links() {
+        let links = internal_links("<a href=\"/blog/post#intro\">a</a> <a href=\"https://example.com/blog/x\">b</a> <a href=\"/blog\">c</a>");
+        assert_eq!(links, vec![(0, "/blog/post#intro".to_string())]);
+    }
+}
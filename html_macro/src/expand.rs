@@ -0,0 +1,376 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::node::{Attribute, AttributeValue, Element, Node, Nodes};
+
+// tags with their own handling below, everything else is in `ELEMENTS`
+const SPECIAL_ELEMENTS: &[&str] = &["a", "hr", "img", "li", "ol", "ul"];
+
+/// A tag with a builder in `crate::html` that takes `element`s (unless void), the common
+/// attributes and its own optional ones as (attribute, builder field)
+struct Spec {
+    tag: &'static str,
+    builder: &'static str,
+    own: &'static [(&'static str, &'static str)],
+}
+
+const ELEMENTS: &[Spec] = &[
+    Spec { tag: "html", builder: "DocumentBuilder", own: &[("lang", "lang")] },
+    Spec { tag: "head", builder: "HeadBuilder", own: &[] },
+    Spec { tag: "title", builder: "TitleBuilder", own: &[] },
+    Spec { tag: "script", builder: "ScriptBuilder", own: &[("src", "src"), ("integrity", "integrity"), ("crossorigin", "crossorigin"), ("nonce", "nonce")] },
+    Spec { tag: "body", builder: "BodyBuilder", own: &[] },
+    Spec { tag: "div", builder: "DivBuilder", own: &[] },
+    Spec { tag: "p", builder: "ParagraphBuilder", own: &[] },
+    Spec { tag: "h1", builder: "Header1Builder", own: &[] },
+    Spec { tag: "h2", builder: "Header2Builder", own: &[] },
+    Spec { tag: "h3", builder: "Header3Builder", own: &[] },
+    Spec { tag: "h4", builder: "Header4Builder", own: &[] },
+    Spec { tag: "h5", builder: "Header5Builder", own: &[] },
+    Spec { tag: "h6", builder: "Header6Builder", own: &[] },
+    Spec { tag: "pre", builder: "PreBuilder", own: &[] },
+    Spec { tag: "code", builder: "CodeBuilder", own: &[] },
+    Spec { tag: "blockquote", builder: "BlockquoteBuilder", own: &[("cite", "cite")] },
+    Spec { tag: "figure", builder: "FigureBuilder", own: &[] },
+    Spec { tag: "figcaption", builder: "FigcaptionBuilder", own: &[] },
+    Spec { tag: "table", builder: "TableBuilder", own: &[] },
+    Spec { tag: "thead", builder: "TableHeadBuilder", own: &[] },
+    Spec { tag: "tbody", builder: "TableBodyBuilder", own: &[] },
+    Spec { tag: "tr", builder: "TableRowBuilder", own: &[] },
+    Spec { tag: "th", builder: "TableHeaderCellBuilder", own: &[] },
+    Spec { tag: "td", builder: "TableCellBuilder", own: &[] },
+    Spec { tag: "nav", builder: "NavBuilder", own: &[] },
+    Spec { tag: "header", builder: "HeaderBuilder", own: &[] },
+    Spec { tag: "footer", builder: "FooterBuilder", own: &[] },
+    Spec { tag: "article", builder: "ArticleBuilder", own: &[] },
+    Spec { tag: "section", builder: "SectionBuilder", own: &[] },
+    Spec { tag: "time", builder: "TimeBuilder", own: &[("datetime", "datetime")] },
+    Spec { tag: "form", builder: "FormBuilder", own: &[("action", "action"), ("method", "method")] },
+    Spec { tag: "button", builder: "ButtonBuilder", own: &[("type", "button_type"), ("name", "name"), ("value", "value")] },
+    Spec { tag: "meta", builder: "MetaBuilder", own: &[("name", "name"), ("property", "property"), ("content", "content"), ("charset", "charset")] },
+    Spec { tag: "link", builder: "LinkBuilder", own: &[("rel", "rel"), ("href", "href"), ("type", "link_type"), ("title", "title"), ("integrity", "integrity"), ("crossorigin", "crossorigin")] },
+    Spec { tag: "input", builder: "InputBuilder", own: &[("type", "input_type"), ("name", "name"), ("value", "value"), ("placeholder", "placeholder")] },
+];
+// what `crate::html::Attributes` can hold, for the elements that take it
+// plus any `data-*` and `aria-*`
+const COMMON_ATTRIBUTES: &[&str] = &[
+    "id", "class", "style", "title", "lang", "rel", "target", "role", "width", "height",
+    "hx-get", "hx-post", "hx-put", "hx-patch", "hx-delete",
+    "hx-target", "hx-swap", "hx-trigger", "hx-push-url", "hx-select", "hx-boost", "hx-indicator", "hx-vals", "hx-confirm",
+];
+// htmx attributes whose values are typed in `crate::html`, so they only take an {expression}
+const TYPED_HTMX_ATTRIBUTES: &[(&str, &str)] = &[
+    ("hx-swap", "Swap"),
+    ("hx-trigger", "Vec<Trigger>"),
+    ("hx-push-url", "PushUrl"),
+    ("hx-boost", "bool"),
+    ("hx-vals", "Vec<(String, String)>"),
+];
+
+pub fn expand(nodes: Nodes) -> syn::Result<TokenStream> {
+    let mut roots = nodes.0.iter().map(expand_node).collect::<syn::Result<Vec<_>>>()?;
+    if roots.len() == 1 {
+        return Ok(roots.remove(0));
+    }
+    Ok(boxed(&roots))
+}
+
+fn expand_node(node: &Node) -> syn::Result<TokenStream> {
+    match node {
+        Node::Text(text) => Ok(quote!(#text)),
+        Node::Expr(expr) => Ok(quote!(#expr)),
+        Node::Element(element) => expand_element(element),
+    }
+}
+
+fn expand_element(element: &Element) -> syn::Result<TokenStream> {
+    let span = element.span;
+    match element.name.as_str() {
+        "a" => {
+            required(element, "href")?;
+            expand_spec(element, &Spec { tag: "a", builder: "AnchorBuilder", own: &[("href", "href")] })
+        }
+        "img" => {
+            check_attributes(element, &["src", "alt"], true)?;
+            let src = string_value(&required(element, "src")?.value);
+            let alt = string_value(&required(element, "alt")?.value);
+            let attributes = common_attributes(element, &["src", "alt"])?.map(|a| quote!(.attributes(#a)));
+            Ok(quote_spanned!(span=> crate::html::ImgBuilder::default().uri(#src).alt_text(#alt) #attributes .build().unwrap()))
+        }
+        list @ ("ul" | "ol") => {
+            check_attributes(element, &[], true)?;
+            let mut items = vec![];
+            let mut item_attributes: Option<Option<TokenStream>> = None;
+            for child in element.children.iter() {
+                let Node::Element(li) = child else {
+                    return Err(syn::Error::new(node_span(child), format!("<{}> can only contain <li> elements", list)));
+                };
+                if li.name != "li" {
+                    return Err(syn::Error::new(li.span, format!("<{}> can only contain <li> elements, found <{}>", list, li.name)));
+                }
+                check_attributes(li, &[], true)?;
+                let attributes = common_attributes(li, &[])?;
+                // lists have one set of attributes for all their items
+                match &item_attributes {
+                    Some(first) if first.as_ref().map(ToString::to_string) != attributes.as_ref().map(ToString::to_string) => {
+                        return Err(syn::Error::new(li.span, format!("every <li> in a <{}> needs the same attributes", list)));
+                    }
+                    Some(_) => {}
+                    None => item_attributes = Some(attributes),
+                }
+                items.push(content(li)?);
+            }
+            let attributes = common_attributes(element, &[])?.map(|a| quote!(.attributes(#a)));
+            let item_attributes = item_attributes.flatten().map(|a| quote!(.item_attributes(#a)));
+            let builder = syn::Ident::new(if list == "ul" { "UlistBuilder" } else { "OlistBuilder" }, span);
+            Ok(quote_spanned!(span=> crate::html::#builder::default() #(.item(#items))* #attributes #item_attributes .build().unwrap()))
+        }
+        "li" => Err(syn::Error::new(span, "<li> has to be directly inside a <ul> or <ol>")),
+        "hr" => {
+            check_attributes(element, &[], false)?;
+            Ok(quote_spanned!(span=> crate::html::Hr))
+        }
+        name => match ELEMENTS.iter().find(|spec| spec.tag == name) {
+            Some(spec) => expand_spec(element, spec),
+            None => {
+                let mut known: Vec<&str> = ELEMENTS.iter().map(|spec| spec.tag).chain(SPECIAL_ELEMENTS.iter().copied()).collect();
+                known.sort();
+                Err(syn::Error::new(span, format!("unknown element <{}>, expected one of: {}", name, known.join(", "))))
+            }
+        },
+    }
+}
+
+fn expand_spec(element: &Element, spec: &Spec) -> syn::Result<TokenStream> {
+    let span = element.span;
+    let own: Vec<&str> = spec.own.iter().map(|(attribute, _)| *attribute).collect();
+    check_attributes(element, &own, true)?;
+    let children = expand_children(element)?;
+    let own_setters = spec.own.iter().filter_map(|(attribute, field)| {
+        let value = string_value(&element.attributes.iter().find(|a| a.name == *attribute)?.value);
+        let field = syn::Ident::new(field, span);
+        Some(quote!(.#field(#value)))
+    });
+    let attributes = common_attributes(element, &own)?.map(|a| quote!(.attributes(#a)));
+    let builder = syn::Ident::new(spec.builder, span);
+    Ok(quote_spanned!(span=> crate::html::#builder::default() #(.element(#children))* #(#own_setters)* #attributes .build().unwrap()))
+}
+
+fn expand_children(element: &Element) -> syn::Result<Vec<TokenStream>> {
+    element.children.iter().map(expand_node).collect()
+}
+
+/// An element's children as a single `IntoHtml`
+fn content(element: &Element) -> syn::Result<TokenStream> {
+    let mut children = expand_children(element)?;
+    Ok(match children.len() {
+        0 => quote!(""),
+        1 => children.remove(0),
+        _ => boxed(&children),
+    })
+}
+
+fn boxed(nodes: &[TokenStream]) -> TokenStream {
+    quote!(::std::vec![#(<::std::boxed::Box<dyn crate::html::IntoHtml>>::from(#nodes)),*])
+}
+
+/// Rejects unknown and repeated attributes. `own` are the ones the element's type takes directly,
+/// `common` whether it also takes `crate::html::Attributes`.
+fn check_attributes(element: &Element, own: &[&str], common: bool) -> syn::Result<()> {
+    for (i, attribute) in element.attributes.iter().enumerate() {
+        let name = attribute.name.as_str();
+        if let Some((_, rest)) = prefixed(name).filter(|_| common)
+            && !rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+            return Err(syn::Error::new(attribute.span, format!("`{}` can only have ascii letters, digits, `-`, `_` and `.` after the prefix", name)));
+        }
+        let allowed = own.contains(&name) || (common && (COMMON_ATTRIBUTES.contains(&name) || prefixed(name).is_some()));
+        if !allowed {
+            let mut allowed = own.to_vec();
+            if common {
+                allowed.extend(COMMON_ATTRIBUTES);
+                allowed.extend(["data-*", "aria-*"]);
+            }
+            let allowed = if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") };
+            return Err(syn::Error::new(attribute.span, format!("<{}> doesn't take `{}`, it takes: {}", element.name, name, allowed)));
+        }
+        if element.attributes[..i].iter().any(|a| a.name == name) {
+            return Err(syn::Error::new(attribute.span, format!("`{}` is set twice", name)));
+        }
+    }
+    Ok(())
+}
+
+fn required<'a>(element: &'a Element, name: &str) -> syn::Result<&'a Attribute> {
+    element.attributes.iter()
+        .find(|a| a.name == name)
+        .ok_or_else(|| syn::Error::new(element.span, format!("<{}> needs `{}`", element.name, name)))
+}
+
+/// The `crate::html::Attributes` for everything that isn't one of `own`, if there is anything
+fn common_attributes(element: &Element, own: &[&str]) -> syn::Result<Option<TokenStream>> {
+    let mut setters = vec![];
+    for attribute in element.attributes.iter().filter(|a| !own.contains(&a.name.as_str())) {
+        let span = attribute.span;
+        setters.push(match (attribute.name.as_str(), &attribute.value) {
+            ("class", AttributeValue::Str(classes)) => {
+                let classes = classes.value();
+                let classes = classes.split_whitespace();
+                quote_spanned!(span=> .attribute(crate::html::Attribute::CLASS(::std::vec![#(#classes.to_string()),*])))
+            }
+            ("class", AttributeValue::Expr(expr)) => {
+                quote_spanned!(span=> .attribute(crate::html::Attribute::CLASS(::std::vec![::std::string::ToString::to_string(&#expr)])))
+            }
+            ("width" | "height", value) => {
+                let pixels = pixels(&attribute.name, value)?;
+                let variant = syn::Ident::new(&attribute.name.to_ascii_uppercase(), span);
+                quote_spanned!(span=> .attribute(crate::html::Attribute::#variant(#pixels)))
+            }
+            ("style", AttributeValue::Expr(style)) => quote_spanned!(span=> .attribute(crate::html::Attribute::STYLE(#style))),
+            ("style", value) => return Err(syn::Error::new(value_span(value), "`style` takes a `Style` {expression}")),
+            (name @ ("id" | "title" | "lang" | "rel" | "target" | "role"), value) => {
+                let variant = syn::Ident::new(&name.to_ascii_uppercase(), span);
+                let value = string_value(value);
+                quote_spanned!(span=> .attribute(crate::html::Attribute::#variant(#value)))
+            }
+            (name, value) if prefixed(name).is_some() => {
+                let (prefix, rest) = prefixed(name).unwrap();
+                let variant = syn::Ident::new(&prefix.to_ascii_uppercase(), span);
+                let value = string_value(value);
+                quote_spanned!(span=> .attribute(crate::html::Attribute::#variant(crate::html::PrefixedName::new(#rest).unwrap(), #value)))
+            }
+            (htmx, value) if htmx.starts_with("hx-") => {
+                let variant = syn::Ident::new(&htmx_variant(htmx), span);
+                let value = match (TYPED_HTMX_ATTRIBUTES.iter().find(|(name, _)| *name == htmx), value) {
+                    (Some(_), AttributeValue::Expr(expr)) => quote!(#expr),
+                    (Some((_, typed)), value) => {
+                        return Err(syn::Error::new(value_span(value), format!("`{}` takes a `{}` {{expression}}", htmx, typed)));
+                    }
+                    (None, value) => string_value(value),
+                };
+                quote_spanned!(span=> .htmx_attribute(crate::html::HtmxAttributes::#variant(#value)))
+            }
+            (name, _) => return Err(syn::Error::new(span, format!("unexpected value for `{}`", name))),
+        });
+    }
+    if setters.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(quote!(crate::html::AttributesBuilder::default() #(#setters)* .build().unwrap())))
+}
+
+/// `hx-get` is `GET`, `hx-push-url` is `PUSH_URL`
+fn htmx_variant(attribute: &str) -> String {
+    attribute["hx-".len()..].to_ascii_uppercase().replace('-', "_")
+}
+
+/// `data-post` is ("data", "post"), for the attributes named by a prefix
+fn prefixed(name: &str) -> Option<(&str, &str)> {
+    ["data", "aria"].into_iter()
+        .find_map(|prefix| Some((prefix, name.strip_prefix(prefix)?.strip_prefix('-')?)))
+        .filter(|(_, rest)| !rest.is_empty())
+}
+
+/// `width=150`, `width="150"` or `width={expr}`, always pixels
+fn pixels(name: &str, value: &AttributeValue) -> syn::Result<TokenStream> {
+    match value {
+        AttributeValue::Int(amount) => Ok(quote!(#amount)),
+        AttributeValue::Expr(amount) => Ok(quote!(#amount)),
+        AttributeValue::Str(value) => {
+            let amount = value.value().parse::<u32>()
+                .map_err(|_| syn::Error::new(value.span(), format!("`{}` is a whole number of pixels, other units go in `style`", name)))?;
+            Ok(quote!(#amount))
+        }
+    }
+}
+
+fn string_value(value: &AttributeValue) -> TokenStream {
+    match value {
+        AttributeValue::Str(value) => quote!(::std::string::ToString::to_string(#value)),
+        AttributeValue::Int(value) => quote!(::std::string::ToString::to_string(&#value)),
+        AttributeValue::Expr(value) => quote!(::std::string::ToString::to_string(&#value)),
+    }
+}
+
+fn value_span(value: &AttributeValue) -> proc_macro2::Span {
+    match value {
+        AttributeValue::Str(value) => value.span(),
+        AttributeValue::Int(value) => value.span(),
+        AttributeValue::Expr(value) => value.span(),
+    }
+}
+
+fn node_span(node: &Node) -> proc_macro2::Span {
+    match node {
+        Node::Element(element) => element.span,
+        Node::Text(text) => text.span(),
+        Node::Expr(expr) => expr.span(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn expand_str(input: &str) -> syn::Result<String> {
+        expand(syn::parse_str::<Nodes>(input)?).map(|tokens| tokens.to_string())
+    }
+
+    fn error(input: &str) -> String {
+        match expand_str(input) {
+            Ok(tokens) => panic!("expected an error, got {}", tokens),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let tokens = expand_str("<div class=\"a b\"><a href={url}>\"x\"</a><img src=\"/l.png\" alt=\"logo\" width=150 /></div>").unwrap();
+        assert!(tokens.contains("DivBuilder"));
+        assert!(tokens.contains("CLASS (:: std :: vec ! [\"a\" . to_string () , \"b\" . to_string ()])"));
+        assert!(tokens.contains("AnchorBuilder :: default () . element (\"x\") . href (:: std :: string :: ToString :: to_string (& url))"));
+        assert!(tokens.contains("WIDTH (150)"));
+
+        let accessible = expand_str("<nav id=\"main\" aria-label=\"Sections\" data-count={n} style={style}></nav>").unwrap();
+        assert!(accessible.contains("Attribute :: ID (:: std :: string :: ToString :: to_string (\"main\"))"));
+        assert!(accessible.contains("Attribute :: ARIA (crate :: html :: PrefixedName :: new (\"label\") . unwrap () , :: std :: string :: ToString :: to_string (\"Sections\"))"));
+        assert!(accessible.contains("Attribute :: DATA (crate :: html :: PrefixedName :: new (\"count\") . unwrap () , :: std :: string :: ToString :: to_string (& n))"));
+        assert!(accessible.contains("Attribute :: STYLE (style)"));
+
+        let htmx = expand_str("<div hx-get=\"/more\" hx-target=\"#posts\" hx-swap={swap} hx-push-url={PushUrl::Yes}></div>").unwrap();
+        assert!(htmx.contains("HtmxAttributes :: GET (:: std :: string :: ToString :: to_string (\"/more\"))"));
+        assert!(htmx.contains("HtmxAttributes :: TARGET (:: std :: string :: ToString :: to_string (\"#posts\"))"));
+        assert!(htmx.contains("HtmxAttributes :: SWAP (swap)"));
+        assert!(htmx.contains("HtmxAttributes :: PUSH_URL (PushUrl :: Yes)"));
+
+        let head = expand_str("<head><meta property=\"og:title\" content={title} /><link rel=\"icon\" type=\"image/png\" href=\"/f.png\"></head>").unwrap();
+        assert!(head.contains("MetaBuilder :: default () . property (:: std :: string :: ToString :: to_string (\"og:title\")) . content (:: std :: string :: ToString :: to_string (& title))"));
+        assert!(head.contains(". link_type (:: std :: string :: ToString :: to_string (\"image/png\"))"));
+
+        let list = expand_str("<ol class=\"steps\"><li>\"a\"</li></ol>").unwrap();
+        assert!(list.contains("OlistBuilder :: default () . item (\"a\")"));
+
+        let siblings = expand_str("<hr /> \"text\" {child}").unwrap();
+        assert!(siblings.starts_with(":: std :: vec !"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(error("<blink>\"x\"</blink>").starts_with("unknown element <blink>, expected one of: a, article, blockquote, body, button, code, div,"));
+        assert_eq!(error("<div clas=\"x\"></div>"), "<div> doesn't take `clas`, it takes: id, class, style, title, lang, rel, target, role, width, height, \
+            hx-get, hx-post, hx-put, hx-patch, hx-delete, hx-target, hx-swap, hx-trigger, hx-push-url, hx-select, hx-boost, hx-indicator, hx-vals, hx-confirm, data-*, aria-*");
+        assert_eq!(error("<a>\"x\"</a>"), "<a> needs `href`");
+        assert_eq!(error("<div></span>"), "expected </div>, found </span>");
+        assert_eq!(error("<div>"), "<div> is never closed");
+        assert_eq!(error("<ul><li class=\"a\">\"x\"</li><li>\"y\"</li></ul>"), "every <li> in a <ul> needs the same attributes");
+        assert_eq!(error("<ul>\"x\"</ul>"), "<ul> can only contain <li> elements");
+        assert_eq!(error("<ol><p>\"x\"</p></ol>"), "<ol> can only contain <li> elements, found <p>");
+        assert_eq!(error("<time date=\"2024\"></time>"), "<time> doesn't take `date`, it takes: datetime, id, class, style, title, lang, rel, target, role, width, height, \
+            hx-get, hx-post, hx-put, hx-patch, hx-delete, hx-target, hx-swap, hx-trigger, hx-push-url, hx-select, hx-boost, hx-indicator, hx-vals, hx-confirm, data-*, aria-*");
+        assert_eq!(error("<img src=\"a\" alt=\"b\" width=\"150vw\">"), "`width` is a whole number of pixels, other units go in `style`");
+        assert_eq!(error("<div style=\"margin: 1em\"></div>"), "`style` takes a `Style` {expression}");
+        assert_eq!(error("<div data-café=\"x\"></div>"), "`data-café` can only have ascii letters, digits, `-`, `_` and `.` after the prefix");
+        assert_eq!(error("<div class=\"a\" class=\"b\"></div>"), "`class` is set twice");
+        assert_eq!(error("<div hx-swap=\"outerHTML\"></div>"), "`hx-swap` takes a `Swap` {expression}");
+    }
+}
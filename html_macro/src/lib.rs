@@ -0,0 +1,27 @@
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
+
+use crate::node::Nodes;
+
+mod expand;
+mod node;
+
+/// JSX-like markup that expands to the `crate::html` types, with tag and attribute names checked
+/// at compile time. Text goes in string literals and `{expressions}` can be anything implementing
+/// `IntoHtml`, so plain strings get escaped and `Raw` is still needed for trusted markup.
+/// Several top level nodes expand to a `Vec<Box<dyn IntoHtml>>`.
+///
+/// ```ignore
+/// html! {
+///     <div class="post">
+///         <a href={format!("/blog/{}", post.slug)}>{post.title}</a>
+///         <img src="/logo.png" alt="logo" width=150 />
+///     </div>
+/// }
+/// ```
+#[proc_macro]
+pub fn html(input: TokenStream) -> TokenStream {
+    expand::expand(parse_macro_input!(input as Nodes))
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
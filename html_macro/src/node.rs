@@ -0,0 +1,133 @@
+use proc_macro2::Span;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{braced, Expr, Ident, LitInt, LitStr, Token};
+
+// `<img ...>` and `<img ... />` both work, neither has children or an end tag
+pub const VOID_ELEMENTS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+
+/// Everything inside `html! { ... }`, one or more sibling nodes
+pub struct Nodes(pub Vec<Node>);
+
+pub enum Node {
+    Element(Element),
+    /// `"literal text"`
+    Text(LitStr),
+    /// `{expression}`, anything that implements `IntoHtml`
+    Expr(Expr),
+}
+
+pub struct Element {
+    pub name: String,
+    pub span: Span,
+    pub attributes: Vec<Attribute>,
+    pub children: Vec<Node>,
+}
+
+pub struct Attribute {
+    pub name: String,
+    pub span: Span,
+    pub value: AttributeValue,
+}
+
+pub enum AttributeValue {
+    Str(LitStr),
+    Int(LitInt),
+    Expr(Expr),
+}
+
+impl Parse for Nodes {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut nodes = vec![];
+        while !input.is_empty() {
+            nodes.push(input.parse()?);
+        }
+        if nodes.is_empty() {
+            return Err(input.error("html! needs at least one node"));
+        }
+        Ok(Nodes(nodes))
+    }
+}
+
+impl Parse for Node {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![<]) {
+            Ok(Node::Element(input.parse()?))
+        } else if input.peek(LitStr) {
+            Ok(Node::Text(input.parse()?))
+        } else if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            Ok(Node::Expr(content.parse()?))
+        } else {
+            Err(input.error("expected an element, a \"string\" or an {expression}"))
+        }
+    }
+}
+
+impl Parse for Element {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![<]>()?;
+        let (name, span) = parse_name(input)?;
+        let mut attributes = vec![];
+        while !input.peek(Token![>]) && !input.peek(Token![/]) {
+            attributes.push(input.parse()?);
+        }
+        if input.parse::<Option<Token![/]>>()?.is_some() {
+            input.parse::<Token![>]>()?;
+            return Ok(Element { name, span, attributes, children: vec![] });
+        }
+        input.parse::<Token![>]>()?;
+        if VOID_ELEMENTS.contains(&name.as_str()) {
+            return Ok(Element { name, span, attributes, children: vec![] });
+        }
+
+        let mut children = vec![];
+        while !(input.peek(Token![<]) && input.peek2(Token![/])) {
+            if input.is_empty() {
+                return Err(syn::Error::new(span, format!("<{}> is never closed", name)));
+            }
+            children.push(input.parse()?);
+        }
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![/]>()?;
+        let (closing, closing_span) = parse_name(input)?;
+        if closing != name {
+            return Err(syn::Error::new(closing_span, format!("expected </{}>, found </{}>", name, closing)));
+        }
+        input.parse::<Token![>]>()?;
+        Ok(Element { name, span, attributes, children })
+    }
+}
+
+impl Parse for Attribute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (name, span) = parse_name(input)?;
+        input.parse::<Token![=]>()?;
+        let value = if input.peek(LitStr) {
+            AttributeValue::Str(input.parse()?)
+        } else if input.peek(LitInt) {
+            AttributeValue::Int(input.parse()?)
+        } else if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            AttributeValue::Expr(content.parse()?)
+        } else {
+            return Err(input.error(format!("expected a value for `{}`: a \"string\", a number or an {{expression}}", name)));
+        };
+        Ok(Attribute { name, span, value })
+    }
+}
+
+/// Tag and attribute names: idents joined by dashes, keywords allowed (`hx-get`, `type`)
+fn parse_name(input: ParseStream) -> syn::Result<(String, Span)> {
+    let first = Ident::parse_any(input)?;
+    let span = first.span();
+    let mut name = first.to_string();
+    while input.peek(Token![-]) {
+        input.parse::<Token![-]>()?;
+        name.push('-');
+        name.push_str(&Ident::parse_any(input)?.to_string());
+    }
+    Ok((name, span))
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+// everything besides the posts that goes into a rendered page
+const TEMPLATE_SOURCES: &[&str] = &["src", "assets", "css", "js", "../blog_files_macro/src", "../html_macro/src", "../precompress/src", "../Cargo.lock"];
+
+fn main() {
+    // list_blog_files! globs this directory, so adding or removing a post has to trigger a rebuild
+    println!("cargo:rerun-if-changed=blog");
+
+    // Pages are rendered at runtime, but only from what's compiled in, so their ETags can be worked
+    // out here. TEMPLATE_HASH changes with anything a page is built from except the posts, a post
+    // page's ETag combines it with the post's own content hash. SITE_HASH covers the posts too,
+    // for pages like the index that list all of them.
+    let mut template = Sha256::new();
+    for source in TEMPLATE_SOURCES {
+        println!("cargo:rerun-if-changed={}", source);
+        hash_path(&mut template, Path::new(source));
+    }
+    let mut site = template.clone();
+    hash_path(&mut site, Path::new("blog"));
+    println!("cargo:rustc-env=TEMPLATE_HASH={}", hex(template.finalize().as_slice()));
+    println!("cargo:rustc-env=SITE_HASH={}", hex(site.finalize().as_slice()));
+}
+
+/// Hashes a file, or every file under a directory in a stable order, names included
+fn hash_path(hasher: &mut Sha256, path: &Path) {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<PathBuf>>();
+        entries.sort();
+        for entry in entries {
+            hash_path(hasher, &entry);
+        }
+    } else if let Ok(bytes) = fs::read(path) {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(&bytes);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,17 @@
+#!/usr/bin/env sh
+# Fetches the pinned htmx build that assets::HTMX embeds.
+# Commit the downloaded file; the build reads it from here.
+set -eu
+cd "$(dirname "$0")"
+
+HTMX_VERSION=1.9.10
+# the integrity hash published for htmx, anything else means the download isn't what we pinned
+HTMX_SRI=sha384-D1Kt99CQMDuVetoL1lrYwg5t+9QdHe7NLX/SoJYkXDFfX37iInKRy5xLSi8nO7UC
+
+curl -sSfL -o "htmx-$HTMX_VERSION.min.js" "https://unpkg.com/htmx.org@$HTMX_VERSION/dist/htmx.min.js"
+
+actual="sha384-$(openssl dgst -sha384 -binary "htmx-$HTMX_VERSION.min.js" | openssl base64 -A)"
+if [ "$actual" != "$HTMX_SRI" ]; then
+    echo "htmx-$HTMX_VERSION.min.js: expected $HTMX_SRI, got $actual" >&2
+    exit 1
+fi
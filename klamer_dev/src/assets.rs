@@ -0,0 +1,173 @@
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::Router;
+use blog_files_macro::static_asset;
+use html_macro::html;
+
+use crate::cache::IMMUTABLE;
+use crate::encoding::Variants;
+use crate::html::{Link, Script};
+
+/// A file compiled into the binary, served from `fingerprinted_path` with the content hash in the
+/// name so it can be cached forever. Pages link to the fingerprinted path, `path` just redirects.
+pub struct StaticAsset {
+    pub path: &'static str,
+    pub fingerprinted_path: &'static str,
+    pub bytes: &'static [u8],
+    /// Compressed at build time, `None` when compression doesn't pay off
+    pub gzip: Option<&'static [u8]>,
+    pub brotli: Option<&'static [u8]>,
+    /// Quoted content hash, computed at build time
+    pub etag: &'static str,
+    /// Subresource integrity hash for `<script>` and `<link>` tags
+    pub integrity: &'static str,
+}
+
+pub const FAVICON: StaticAsset = static_asset!("/favicon.png", "assets/k_logo.dev.png");
+pub const LOGO: StaticAsset = static_asset!("/logo.png", "assets/klamer.dev.png");
+pub const BASE_CSS: StaticAsset = static_asset!("/base.css", "css/base.css");
+pub const PRISM_CSS: StaticAsset = static_asset!("/prism.css", "css/prism-gruvbox-dark.css");
+// pinned version fetched by js/vendor.sh, anything but the published build fails to compile
+pub const HTMX: StaticAsset = static_asset!("/htmx.min.js", "js/htmx-1.9.10.min.js", "sha384-D1Kt99CQMDuVetoL1lrYwg5t+9QdHe7NLX/SoJYkXDFfX37iInKRy5xLSi8nO7UC");
+
+pub const STATIC_ASSETS: &[StaticAsset] = &[
+    FAVICON,
+    LOGO,
+    BASE_CSS,
+    PRISM_CSS,
+    HTMX,
+];
+
+impl StaticAsset {
+    pub fn content_type(&self) -> &'static str {
+        content_type(self.path)
+    }
+
+    pub fn stylesheet(&self) -> Link {
+        html! { <link rel="stylesheet" href={self.fingerprinted_path} integrity={self.integrity} /> }
+    }
+
+    pub fn script(&self) -> Script {
+        html! { <script src={self.fingerprinted_path} integrity={self.integrity}></script> }
+    }
+
+    fn variants(&self) -> Variants {
+        Variants {
+            identity: Bytes::from_static(self.bytes),
+            gzip: self.gzip.map(Bytes::from_static),
+            brotli: self.brotli.map(Bytes::from_static),
+        }
+    }
+
+    /// The best precompressed variant for the request's `Accept-Encoding`
+    pub fn respond(&self, request_headers: &HeaderMap) -> Response {
+        let variants = self.variants();
+        let (encoding, body) = variants.select(request_headers);
+        let mut response = ([
+            (header::CONTENT_TYPE, HeaderValue::from_static(self.content_type())),
+            (header::ETAG, encoding.etag(self.etag)),
+            (header::CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE)),
+        ], body).into_response();
+        variants.apply_headers(encoding, &mut response);
+        response
+    }
+}
+
+/// Routes for every asset in `STATIC_ASSETS`. The plain paths redirect temporarily, since
+/// where they point changes with every deploy.
+pub fn router() -> Router {
+    STATIC_ASSETS.iter().fold(Router::new(), |router, asset| {
+        router.route(asset.fingerprinted_path, get(move |headers: HeaderMap| async move { asset.respond(&headers) }))
+            .route(asset.path, get(move || async move { Redirect::temporary(asset.fingerprinted_path) }))
+    })
+}
+
+/// MIME type from the file extension, falling back to `application/octet-stream`
+pub fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).as_deref() {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("html") => "text/html; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml; charset=utf-8",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use sha2::{Digest, Sha384};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::html::IntoHtml;
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type("/base.css"), "text/css; charset=utf-8");
+        assert_eq!(content_type("/a/b/logo.PNG"), "image/png");
+        assert_eq!(content_type("/no_extension"), "application/octet-stream");
+    }
+
+    #[tokio::test]
+    async fn test_asset_headers() {
+        for (asset, expected) in [
+            (&FAVICON, "image/png"),
+            (&LOGO, "image/png"),
+            (&BASE_CSS, "text/css; charset=utf-8"),
+            (&PRISM_CSS, "text/css; charset=utf-8"),
+            (&HTMX, "text/javascript; charset=utf-8"),
+        ] {
+            let response = router().oneshot(Request::get(asset.fingerprinted_path).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", asset.fingerprinted_path);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], expected, "{}", asset.path);
+            assert_eq!(response.headers()[header::ETAG], asset.etag, "{}", asset.path);
+            assert_eq!(response.headers()[header::CACHE_CONTROL], IMMUTABLE, "{}", asset.path);
+            assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), asset.bytes);
+        }
+    }
+
+    #[test]
+    fn test_integrity() {
+        let digest = Sha384::digest(BASE_CSS.bytes);
+        assert_eq!(BASE_CSS.integrity, format!("sha384-{}", BASE64_STANDARD.encode(digest)));
+        assert!(BASE_CSS.stylesheet().html_string().contains(&format!("integrity=\"{}\"", BASE_CSS.integrity)));
+    }
+
+    #[tokio::test]
+    async fn test_precompressed_variants() {
+        assert!(FAVICON.gzip.is_none() && FAVICON.brotli.is_none());
+        for (accept_encoding, expected) in [("gzip", BASE_CSS.gzip.unwrap()), ("gzip, br", BASE_CSS.brotli.unwrap())] {
+            let response = router().oneshot(Request::get(BASE_CSS.fingerprinted_path)
+                .header(header::ACCEPT_ENCODING, accept_encoding)
+                .body(Body::empty()).unwrap()).await.unwrap();
+            let content_encoding = response.headers()[header::CONTENT_ENCODING].to_str().unwrap().to_string();
+            assert_eq!(response.headers()[header::VARY], "accept-encoding");
+            assert!(response.headers()[header::ETAG].to_str().unwrap().ends_with(&format!("-{}\"", content_encoding)));
+            assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plain_paths_redirect() {
+        for asset in STATIC_ASSETS {
+            assert!(asset.fingerprinted_path.contains(&asset.etag[1..9]), "{}", asset.fingerprinted_path);
+            let response = router().oneshot(Request::get(asset.path).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT, "{}", asset.path);
+            assert_eq!(response.headers()[header::LOCATION], asset.fingerprinted_path);
+        }
+    }
+}
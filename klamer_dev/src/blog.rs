@@ -0,0 +1,82 @@
+use blog_files_macro::list_blog_files;
+
+/// Every post in `klamer_dev/blog`, newest first, with metadata worked out at compile time by `list_blog_files!`
+/// from markdown front matter or from the `<h1>` and `post-metadata` line of html posts.
+const BLOG_POSTS: &[BlogPost] = list_blog_files!();
+
+pub struct BlogPost {
+    pub slug: &'static str,
+    pub title: &'static str,
+    pub published: PostDate,
+    /// Front matter description, or the start of the first paragraph
+    pub summary: &'static str,
+    // generated with the rest of the metadata, nothing shows it yet
+    #[allow(dead_code)]
+    pub word_count: usize,
+    pub tags: &'static [&'static str],
+    /// Hex sha256 of the source file, so it changes with the metadata as well as the body
+    pub content_hash: &'static str,
+    pub draft: bool,
+    pub content: &'static str,
+}
+
+impl BlogPost {
+    /// Drafts are only served from debug builds
+    pub fn is_published(&self) -> bool {
+        cfg!(debug_assertions) || !self.draft
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PostDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+impl PostDate {
+    pub fn month_name(&self) -> &'static str {
+        MONTHS[(self.month - 1) as usize]
+    }
+
+    /// Midnight UTC, for Atom: `2025-05-01T00:00:00Z`
+    pub fn rfc3339(&self) -> String {
+        format!("{}T00:00:00Z", self)
+    }
+
+    /// Midnight UTC, for RSS: `Thu, 01 May 2025 00:00:00 +0000`
+    pub fn rfc2822(&self) -> String {
+        format!("{}, {:02} {} {:04} 00:00:00 +0000", WEEKDAYS[self.weekday()], self.day, &MONTHS[(self.month - 1) as usize][..3], self.year)
+    }
+
+    /// 0 is Sunday
+    fn weekday(&self) -> usize {
+        // days since 1970-01-01 (a Thursday), from Howard Hinnant's days_from_civil
+        let (month, day) = (self.month as i64, self.day as i64);
+        let year = self.year as i64 - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+        (days + 4).rem_euclid(7) as usize
+    }
+}
+
+impl std::fmt::Display for PostDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Published posts, newest first
+pub fn posts() -> impl Iterator<Item = &'static BlogPost> {
+    BLOG_POSTS.iter().filter(|p| p.is_published())
+}
+
+pub fn find_post(slug: &str) -> Option<&'static BlogPost> {
+    posts().find(|p| p.slug == slug)
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use tower::ServiceExt;
+
+use crate::encoding::Variants;
+use crate::htmx::HxRequest;
+
+/// For anything whose url changes with its content
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Keep a copy but check the ETag before using it
+pub const REVALIDATE: &str = "no-cache";
+
+/// What every page is rendered from besides the posts, hashed by build.rs
+const TEMPLATE_HASH: &str = env!("TEMPLATE_HASH");
+/// `TEMPLATE_HASH` plus every post
+const SITE_HASH: &str = env!("SITE_HASH");
+
+// Every page is rendered from content compiled into the binary, so a path always renders to the
+// same bytes. Render it once, compress it, and serve it from memory after that. htmx requests can
+// get just a fragment of the page, so they're cached under their own key.
+// Only successful responses are cached, so the keys are the router's fixed paths and one or two
+// per post. Percent encoding the same path differently gets it another key though, the limit
+// keeps that from growing the cache without bound.
+static RENDERED: LazyLock<RwLock<HashMap<String, Arc<CachedResponse>>>> = LazyLock::new(Default::default);
+const MAX_RENDERED: usize = 1024;
+
+/// Set as a response extension by handlers whose output only depends on one post, so its ETag
+/// comes from the post's `content_hash` and publishing another post doesn't change it
+#[derive(Clone, Copy)]
+pub struct ContentHash(pub &'static str);
+
+struct CachedResponse {
+    headers: HeaderMap,
+    variants: Variants,
+    etag: String,
+}
+
+impl CachedResponse {
+    /// Compressing happens here, once per page rather than once per request, on the blocking pool
+    /// since brotli at its best takes a while
+    async fn new(headers: HeaderMap, body: Bytes, etag: String) -> Self {
+        tokio::task::spawn_blocking(move || {
+            let variants = Variants::compress(body, headers.get(header::CONTENT_TYPE));
+            CachedResponse { headers, variants, etag }
+        }).await.expect("compressing doesn't panic")
+    }
+
+    fn respond(&self, request_headers: &HeaderMap) -> Response {
+        let (encoding, body) = self.variants.select(request_headers);
+        let etag = encoding.etag(&self.etag);
+        let mut response = if request_headers.get(header::IF_NONE_MATCH).is_some_and(|v| etag_matches(v, &etag)) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let mut response = Body::from(body).into_response();
+            response.headers_mut().extend(self.headers.clone());
+            response
+        };
+        self.variants.apply_headers(encoding, &mut response);
+        // htmx requests get their own copy, see `conditional_get`
+        response.headers_mut().append(header::VARY, HeaderValue::from_static("hx-request"));
+        response.headers_mut().insert(header::ETAG, etag);
+        response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static(REVALIDATE));
+        response
+    }
+}
+
+/// Strong ETag from the build time hashes, no need to hash the body. A fragment is a different
+/// representation of the same url than the whole page, so it gets its own.
+fn etag(content_hash: Option<ContentHash>, fragment: bool) -> String {
+    let version = match content_hash {
+        Some(ContentHash(hash)) => format!("{}{}", &hash[..16], &TEMPLATE_HASH[..16]),
+        None => SITE_HASH[..32].to_string(),
+    };
+    if fragment { format!("\"{}-htmx\"", version) } else { format!("\"{}\"", version) }
+}
+
+/// `If-None-Match` uses the weak comparison, so `W/` prefixes are ignored
+pub fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default().trim_start_matches("W/");
+    if_none_match.trim() == "*" || if_none_match.split(',').any(|candidate| candidate.trim().trim_start_matches("W/") == etag)
+}
+
+/// Adds ETags to successful GETs and answers `If-None-Match` with `304 Not Modified`. Responses that
+/// already carry an ETag (static assets) are passed through, everything else is rendered and
+/// compressed once, then cached by path.
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    // HEAD can be answered from the cache, but only a GET has the body to fill it
+    let is_get = request.method() == Method::GET;
+    let cacheable = is_get || request.method() == Method::HEAD;
+    let request_headers = request.headers().clone();
+    let fragment = HxRequest::from_headers(&request_headers).is_some_and(|hx| hx.wants_fragment());
+    let path = if fragment { format!("{} (htmx)", request.uri().path()) } else { request.uri().path().to_string() };
+
+    if cacheable && let Some(cached) = RENDERED.read().unwrap().get(&path).cloned() {
+        return cached.respond(&request_headers);
+    }
+
+    let response = next.run(request).await;
+    if let Some(etag) = response.headers().get(header::ETAG) {
+        if request_headers.get(header::IF_NONE_MATCH).is_some_and(|v| etag_matches(v, etag)) {
+            let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+            for name in [header::ETAG, header::CACHE_CONTROL, header::VARY] {
+                if let Some(value) = response.headers().get(&name) {
+                    not_modified.headers_mut().insert(name, value.clone());
+                }
+            }
+            return not_modified;
+        }
+        return response;
+    }
+    // no-store marks bodies that differ per request
+    let no_store = response.headers().get(header::CACHE_CONTROL).and_then(|v| v.to_str().ok()).is_some_and(|v| v.contains("no-store"));
+    if !is_get || response.status() != StatusCode::OK || no_store {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(body) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let etag = etag(parts.extensions.get::<ContentHash>().copied(), fragment);
+    let cached = Arc::new(CachedResponse::new(parts.headers, body, etag).await);
+    let mut rendered = RENDERED.write().unwrap();
+    if rendered.len() < MAX_RENDERED {
+        rendered.insert(path, cached.clone());
+    }
+    drop(rendered);
+    cached.respond(&request_headers)
+}
+
+/// Renders and caches every path up front, so no reader has to wait for a page to be compressed
+pub async fn warm(app: Router, paths: impl IntoIterator<Item = String>) {
+    for path in paths {
+        let request = Request::get(&path).body(Body::empty()).unwrap();
+        if let Ok(response) = app.clone().oneshot(request).await && response.status() != StatusCode::OK {
+            tracing::warn!("warming the page cache: {} returned {}", path, response.status());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        assert!(etag_matches(&HeaderValue::from_static("\"abc\""), &etag));
+        assert!(etag_matches(&HeaderValue::from_static("\"xyz\", W/\"abc\""), &etag));
+        assert!(etag_matches(&HeaderValue::from_static("*"), &etag));
+        assert!(!etag_matches(&HeaderValue::from_static("\"abcd\""), &etag));
+    }
+
+    #[test]
+    fn test_etag() {
+        let post = etag(Some(ContentHash("0123456789abcdef0123456789abcdef")), false);
+        assert_eq!(post, format!("\"0123456789abcdef{}\"", &TEMPLATE_HASH[..16]));
+        assert_eq!(etag(None, false), format!("\"{}\"", &SITE_HASH[..32]));
+        assert_ne!(etag(None, true), etag(None, false));
+    }
+}
@@ -0,0 +1,135 @@
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::Response;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContentEncoding {
+    Brotli,
+    Gzip,
+    Identity,
+}
+
+impl ContentEncoding {
+    fn token(&self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Identity => "identity",
+        }
+    }
+
+    /// Each encoding is its own representation, so it needs its own strong ETag: `"abc"` -> `"abc-br"`
+    pub fn etag(&self, etag: &str) -> HeaderValue {
+        match self {
+            ContentEncoding::Identity => HeaderValue::from_str(etag).unwrap(),
+            encoding => HeaderValue::from_str(&format!("{}-{}\"", etag.trim_end_matches('"'), encoding.token())).unwrap(),
+        }
+    }
+}
+
+/// An identity body and whichever compressed versions of it are worth sending
+pub struct Variants {
+    pub identity: Bytes,
+    pub gzip: Option<Bytes>,
+    pub brotli: Option<Bytes>,
+}
+
+impl Variants {
+    /// Compresses text bodies, anything else is only ever sent as is. Slow on purpose, see `precompress`,
+    /// so keep it off the async threads.
+    pub fn compress(identity: Bytes, content_type: Option<&HeaderValue>) -> Self {
+        if !content_type.and_then(|c| c.to_str().ok()).is_some_and(is_compressible) {
+            return Variants { identity, gzip: None, brotli: None };
+        }
+        let variant = |compressed: Vec<u8>| precompress::pays_off(&identity, &compressed).then(|| Bytes::from(compressed));
+        let gzip = variant(precompress::gzip(&identity));
+        let brotli = variant(precompress::brotli(&identity));
+        Variants { identity, gzip, brotli }
+    }
+
+    /// The smallest variant the request's `Accept-Encoding` allows, brotli first
+    pub fn select(&self, request_headers: &HeaderMap) -> (ContentEncoding, Bytes) {
+        let accept_encoding = request_headers.get(header::ACCEPT_ENCODING).and_then(|v| v.to_str().ok()).unwrap_or_default();
+        if let Some(brotli) = self.brotli.as_ref().filter(|_| accepts(accept_encoding, ContentEncoding::Brotli)) {
+            return (ContentEncoding::Brotli, brotli.clone());
+        }
+        if let Some(gzip) = self.gzip.as_ref().filter(|_| accepts(accept_encoding, ContentEncoding::Gzip)) {
+            return (ContentEncoding::Gzip, gzip.clone());
+        }
+        (ContentEncoding::Identity, self.identity.clone())
+    }
+
+    /// Sets `Content-Encoding` for the chosen variant, and `Vary` whenever there was a choice to make
+    pub fn apply_headers(&self, encoding: ContentEncoding, response: &mut Response) {
+        if encoding != ContentEncoding::Identity {
+            response.headers_mut().insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding.token()));
+        }
+        if self.gzip.is_some() || self.brotli.is_some() {
+            response.headers_mut().insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        }
+    }
+}
+
+/// Whether an `Accept-Encoding` value allows the encoding, honouring `q=0` and `*`
+fn accepts(accept_encoding: &str, encoding: ContentEncoding) -> bool {
+    let mut wildcard = false;
+    for part in accept_encoding.split(',') {
+        let mut params = part.split(';');
+        let token = params.next().unwrap_or_default().trim();
+        let quality = params
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if token.eq_ignore_ascii_case(encoding.token()) {
+            return quality > 0.0;
+        }
+        if token == "*" {
+            wildcard = quality > 0.0;
+        }
+    }
+    wildcard
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || content_type.starts_with("application/xml")
+        || content_type.starts_with("application/atom+xml")
+        || content_type.starts_with("application/rss+xml")
+        || content_type.starts_with("application/json")
+        || content_type.starts_with("image/svg+xml")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_accepts() {
+        assert!(accepts("gzip, deflate, br", ContentEncoding::Brotli));
+        assert!(accepts("gzip;q=0.5", ContentEncoding::Gzip));
+        assert!(!accepts("gzip;q=0, br", ContentEncoding::Gzip));
+        assert!(accepts("*", ContentEncoding::Brotli));
+        assert!(!accepts("*, br;q=0", ContentEncoding::Brotli));
+        assert!(!accepts("", ContentEncoding::Gzip));
+    }
+
+    #[test]
+    fn test_select() {
+        let variants = Variants::compress(Bytes::from("<p>hello</p>".repeat(20)), Some(&HeaderValue::from_static("text/html; charset=utf-8")));
+        let mut headers = HeaderMap::new();
+        assert_eq!(variants.select(&headers).0, ContentEncoding::Identity);
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        assert_eq!(variants.select(&headers).0, ContentEncoding::Gzip);
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip, br"));
+        assert_eq!(variants.select(&headers).0, ContentEncoding::Brotli);
+
+        let png = Variants::compress(Bytes::from_static(b"png"), Some(&HeaderValue::from_static("image/png")));
+        assert_eq!(png.select(&headers).0, ContentEncoding::Identity);
+    }
+
+    #[test]
+    fn test_encoded_etag() {
+        assert_eq!(ContentEncoding::Brotli.etag("\"abc\""), "\"abc-br\"");
+        assert_eq!(ContentEncoding::Identity.etag("\"abc\""), "\"abc\"");
+    }
+}
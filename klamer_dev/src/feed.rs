@@ -0,0 +1,147 @@
+use crate::blog::{self, BlogPost};
+use crate::SITE_URL;
+
+const FEED_TITLE: &str = "Klamer.dev";
+const FEED_DESCRIPTION: &str = "Posts & writing";
+const AUTHOR: &str = "Jack Klamer";
+
+/// Atom 1.0 feed of every published post, served at `/feed.xml`
+pub fn atom() -> String {
+    let updated = blog::posts().next().map(|p| p.published.rfc3339()).unwrap_or_else(|| "1970-01-01T00:00:00Z".to_string());
+    let mut feed = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\
+         <title>{title}</title>\
+         <subtitle>{subtitle}</subtitle>\
+         <link href=\"{SITE_URL}/feed.xml\" rel=\"self\" type=\"application/atom+xml\"/>\
+         <link href=\"{SITE_URL}/blog\" rel=\"alternate\" type=\"text/html\"/>\
+         <id>{SITE_URL}/</id>\
+         <updated>{updated}</updated>\
+         <author><name>{author}</name></author>",
+        title = escape_xml(FEED_TITLE),
+        subtitle = escape_xml(FEED_DESCRIPTION),
+        author = escape_xml(AUTHOR),
+    );
+    for post in blog::posts() {
+        feed += &atom_entry(post);
+    }
+    feed + "</feed>"
+}
+
+fn atom_entry(post: &BlogPost) -> String {
+    let url = post_url(post);
+    let categories = post.tags.iter()
+        .map(|t| format!("<category term=\"{}\"/>", escape_xml(t)))
+        .collect::<String>();
+    format!(
+        "<entry>\
+         <title>{title}</title>\
+         <link href=\"{url}\" rel=\"alternate\" type=\"text/html\"/>\
+         <id>{url}</id>\
+         <published>{published}</published>\
+         <updated>{published}</updated>\
+         <summary>{summary}</summary>\
+         <content type=\"html\" xml:base=\"{url}\">{content}</content>\
+         {categories}\
+         </entry>",
+        title = escape_xml(post.title),
+        url = escape_xml(&url),
+        published = post.published.rfc3339(),
+        summary = escape_xml(post.summary),
+        content = escape_xml(post.content),
+    )
+}
+
+/// RSS 2.0 feed of every published post, served at `/rss.xml`
+pub fn rss() -> String {
+    let last_build = blog::posts().next().map(|p| p.published.rfc2822()).unwrap_or_else(|| "Thu, 01 Jan 1970 00:00:00 +0000".to_string());
+    let mut feed = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:content=\"http://purl.org/rss/1.0/modules/content/\">\
+         <channel>\
+         <title>{title}</title>\
+         <link>{SITE_URL}/blog</link>\
+         <description>{description}</description>\
+         <language>en-us</language>\
+         <lastBuildDate>{last_build}</lastBuildDate>\
+         <atom:link href=\"{SITE_URL}/rss.xml\" rel=\"self\" type=\"application/rss+xml\"/>",
+        title = escape_xml(FEED_TITLE),
+        description = escape_xml(FEED_DESCRIPTION),
+    );
+    for post in blog::posts() {
+        feed += &rss_item(post);
+    }
+    feed + "</channel></rss>"
+}
+
+fn rss_item(post: &BlogPost) -> String {
+    let url = escape_xml(&post_url(post));
+    let categories = post.tags.iter()
+        .map(|t| format!("<category>{}</category>", escape_xml(t)))
+        .collect::<String>();
+    format!(
+        "<item>\
+         <title>{title}</title>\
+         <link>{url}</link>\
+         <guid isPermaLink=\"true\">{url}</guid>\
+         <pubDate>{published}</pubDate>\
+         <description>{summary}</description>\
+         <content:encoded>{content}</content:encoded>\
+         {categories}\
+         </item>",
+        title = escape_xml(post.title),
+        published = post.published.rfc2822(),
+        summary = escape_xml(post.summary),
+        content = escape_xml(post.content),
+    )
+}
+
+fn post_url(post: &BlogPost) -> String {
+    format!("{}/blog/{}", SITE_URL, post.slug)
+}
+
+pub fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blog::PostDate;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("<p class=\"x\">Tom & Jerry's</p>"), "&lt;p class=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/p&gt;");
+    }
+
+    #[test]
+    fn test_feed_dates() {
+        let date = PostDate { year: 2025, month: 5, day: 1 };
+        assert_eq!(date.rfc3339(), "2025-05-01T00:00:00Z");
+        assert_eq!(date.rfc2822(), "Thu, 01 May 2025 00:00:00 +0000");
+        assert_eq!(PostDate { year: 2024, month: 2, day: 29 }.rfc2822(), "Thu, 29 Feb 2024 00:00:00 +0000");
+        assert_eq!(PostDate { year: 2000, month: 1, day: 1 }.rfc2822(), "Sat, 01 Jan 2000 00:00:00 +0000");
+    }
+
+    #[test]
+    fn test_feeds_contain_posts() {
+        let atom = atom();
+        let rss = rss();
+        for post in blog::posts() {
+            assert!(atom.contains(&format!("<id>{}/blog/{}</id>", SITE_URL, post.slug)));
+            assert!(rss.contains(&format!("<guid isPermaLink=\"true\">{}/blog/{}</guid>", SITE_URL, post.slug)));
+        }
+        assert!(!atom.contains("<h1>"));
+    }
+}
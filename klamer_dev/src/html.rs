@@ -1,3 +1,6 @@
+use std::fmt::{self, Write};
+use std::time::Duration;
+
 use axum::response::Html;
 use derive_builder::Builder;
 
@@ -9,82 +12,515 @@ pub struct Attributes {
     pub attributes: Vec<Attribute>,
 }
 
-fn html_element_with_attributes<'a>(component : &str, custom_attributes: Option<Vec<String>>, attributes: &Option<Attributes>) -> String {
-    let mut component = format!("<{} ", component);
-    if let Some(custom_attr) = custom_attributes {
-        component += custom_attr.join(" ").as_str();
-    }
+// elements that can't have content and never get an end tag
+const VOID_ELEMENTS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
 
+/// Start tag with the element's own (name, value) attributes that are set, followed by the common ones
+fn write_start_tag(out: &mut dyn Write, component: &str, custom_attributes: &[(&str, Option<&str>)], attributes: &Option<Attributes>) -> fmt::Result {
+    write!(out, "<{}", component)?;
+    for (name, value) in custom_attributes {
+        if let Some(value) = value {
+            write!(out, " {}=\"{}\"", name, EscapeAttribute(value))?;
+        }
+    }
     if let Some(a) = attributes {
         for h in a.htmx_attributes.iter() {
-            component += format!(" {}", h.to_string().as_str()).as_str();
+            write!(out, " {}", h)?;
         }
         for a in a.attributes.iter() {
-            component += format!(" {}", a.to_string().as_str()).as_str();
+            write!(out, " {}", a)?;
         }
     }
-    component + " />"
+    out.write_char('>')
 }
 
+/// Start tag, content and end tag, or just the start tag for void elements
+fn write_element(out: &mut dyn Write,
+                 component: &str,
+                 custom_attributes: &[(&str, Option<&str>)],
+                 attributes: &Option<Attributes>,
+                 content: impl FnOnce(&mut dyn Write) -> fmt::Result) -> fmt::Result {
+    write_start_tag(out, component, custom_attributes, attributes)?;
+    if VOID_ELEMENTS.contains(&component) {
+        return Ok(());
+    }
+    content(out)?;
+    write!(out, "</{}>", component)
+}
 
 #[derive(Clone)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 pub(crate) enum Attribute {
+    ID(String),
     CLASS(Vec<String>),
+    STYLE(Style),
+    TITLE(String),
+    LANG(String),
+    REL(String),
+    TARGET(String),
+    ROLE(String),
+    /// `data-{name}`
+    DATA(PrefixedName, String),
+    /// `aria-{name}`
+    ARIA(PrefixedName, String),
+    /// Pixels, the only unit the `width` and `height` attributes have. Anything else is `STYLE`.
     WIDTH(u32),
     HEIGHT(u32),
-    MARGIN(u32),
-    WidthPercent(u32),
-    HeightPercent(u32),
-    WidthEm(u32),
-    HeightEm(u32),
-    WidthVw(u32),
-    HeightVw(u32),
 }
 
-impl Attribute {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Attribute::CLASS(s) => format!("class=\"{}\"", s.join(" ")),
-            Attribute::WIDTH(u) => format!("width=\"{}\"", u),
-            Attribute::HEIGHT(u) => format!("height=\"{}\"", u),
-            Attribute::MARGIN(u) => format!("margin=\"{}\"", u),
-            Attribute::WidthPercent(u) => format!("width=\"{}%\"", u),
-            Attribute::HeightPercent(u) => format!("height=\"{}%\"", u),
-            Attribute::WidthEm(u) => format!("width=\"{}em\"", u),
-            Attribute::HeightEm(u) => format!("height=\"{}em\"", u),
-            Attribute::WidthVw(u) => format!("width=\"{}vw\"", u),
-            Attribute::HeightVw(u) => format!("height=\"{}vw\"", u),
+            Attribute::ID(s) => write!(f, "id=\"{}\"", EscapeAttribute(s)),
+            Attribute::CLASS(s) => {
+                f.write_str("class=\"")?;
+                for (i, class) in s.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { " " }, EscapeAttribute(class))?;
+                }
+                f.write_char('"')
+            }
+            Attribute::STYLE(style) => {
+                f.write_str("style=\"")?;
+                write!(EscapeAttributeWriter(f), "{}", style)?;
+                f.write_char('"')
+            }
+            Attribute::TITLE(s) => write!(f, "title=\"{}\"", EscapeAttribute(s)),
+            Attribute::LANG(s) => write!(f, "lang=\"{}\"", EscapeAttribute(s)),
+            Attribute::REL(s) => write!(f, "rel=\"{}\"", EscapeAttribute(s)),
+            Attribute::TARGET(s) => write!(f, "target=\"{}\"", EscapeAttribute(s)),
+            Attribute::ROLE(s) => write!(f, "role=\"{}\"", EscapeAttribute(s)),
+            Attribute::DATA(name, value) => write!(f, "data-{}=\"{}\"", name.0, EscapeAttribute(value)),
+            Attribute::ARIA(name, value) => write!(f, "aria-{}=\"{}\"", name.0, EscapeAttribute(value)),
+            Attribute::WIDTH(u) => write!(f, "width=\"{}\"", u),
+            Attribute::HEIGHT(u) => write!(f, "height=\"{}\"", u),
         }
     }
 }
 
+/// The part of a `data-*` or `aria-*` name after the prefix. Names can't be escaped, so they're
+/// checked when they're made rather than when they're written out.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PrefixedName(String);
+
+impl PrefixedName {
+    /// Lowercased, like the browser does anyway, so `postId` is `postid`. `None` for anything but
+    /// ascii letters, digits, `-`, `_` and `.`.
+    pub fn new(name: &str) -> Option<Self> {
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        valid.then(|| PrefixedName(name.to_ascii_lowercase()))
+    }
+}
+
+/// Inline `style`, one typed declaration at a time. The CSP allows these through
+/// `style-src-attr 'unsafe-inline'`, `<style>` elements are still blocked.
+#[derive(Clone, Builder, Default)]
+#[builder(pattern = "owned")]
+#[allow(dead_code)]
+pub struct Style {
+    #[builder(setter(each(name = "declaration")), default = "vec![]")]
+    pub declarations: Vec<Declaration>,
+}
+
 #[derive(Clone)]
 #[allow(dead_code)]
+pub enum Declaration {
+    Width(Length),
+    Height(Length),
+    MaxWidth(Length),
+    Margin(Length),
+    Padding(Length),
+    TextAlign(TextAlign),
+    Display(Display),
+}
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub enum Length {
+    Zero,
+    Auto,
+    Px(u32),
+    Em(u32),
+    Rem(u32),
+    Percent(u32),
+    Vw(u32),
+    Vh(u32),
+}
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub enum Display {
+    None,
+    Block,
+    Inline,
+    InlineBlock,
+    Flex,
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, declaration) in self.declarations.iter().enumerate() {
+            if i > 0 {
+                f.write_char(' ')?;
+            }
+            match declaration {
+                Declaration::Width(length) => write!(f, "width: {};", length),
+                Declaration::Height(length) => write!(f, "height: {};", length),
+                Declaration::MaxWidth(length) => write!(f, "max-width: {};", length),
+                Declaration::Margin(length) => write!(f, "margin: {};", length),
+                Declaration::Padding(length) => write!(f, "padding: {};", length),
+                Declaration::TextAlign(align) => write!(f, "text-align: {};", match align {
+                    TextAlign::Left => "left",
+                    TextAlign::Center => "center",
+                    TextAlign::Right => "right",
+                }),
+                Declaration::Display(display) => write!(f, "display: {};", match display {
+                    Display::None => "none",
+                    Display::Block => "block",
+                    Display::Inline => "inline",
+                    Display::InlineBlock => "inline-block",
+                    Display::Flex => "flex",
+                }),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Zero => f.write_char('0'),
+            Length::Auto => f.write_str("auto"),
+            Length::Px(u) => write!(f, "{}px", u),
+            Length::Em(u) => write!(f, "{}em", u),
+            Length::Rem(u) => write!(f, "{}rem", u),
+            Length::Percent(u) => write!(f, "{}%", u),
+            Length::Vw(u) => write!(f, "{}vw", u),
+            Length::Vh(u) => write!(f, "{}vh", u),
+        }
+    }
+}
+
+#[derive(Clone)]
+// named after the attribute, `hx-push-url` is `PUSH_URL`
+#[allow(dead_code, non_camel_case_types, clippy::upper_case_acronyms)]
 pub(crate) enum HtmxAttributes {
     GET(String),
     POST(String),
     PUT(String),
     PATCH(String),
     DELETE(String),
+    /// CSS selector, or `this`, `closest ...`, `next ...` etc.
+    TARGET(String),
+    SWAP(Swap),
+    TRIGGER(Vec<Trigger>),
+    PUSH_URL(PushUrl),
+    SELECT(String),
+    BOOST(bool),
+    INDICATOR(String),
+    /// Extra parameters sent with the request, as a JSON object of strings
+    VALS(Vec<(String, String)>),
+    CONFIRM(String),
+}
+
+impl std::fmt::Display for HtmxAttributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, value): (&str, &dyn fmt::Display) = match self {
+            HtmxAttributes::GET(s) => ("hx-get", s),
+            HtmxAttributes::POST(s) => ("hx-post", s),
+            HtmxAttributes::PUT(s) => ("hx-put", s),
+            HtmxAttributes::PATCH(s) => ("hx-patch", s),
+            HtmxAttributes::DELETE(s) => ("hx-delete", s),
+            HtmxAttributes::TARGET(s) => ("hx-target", s),
+            HtmxAttributes::SWAP(swap) => ("hx-swap", swap),
+            HtmxAttributes::TRIGGER(triggers) => ("hx-trigger", &Separated(triggers, ", ")),
+            HtmxAttributes::PUSH_URL(push_url) => ("hx-push-url", push_url),
+            HtmxAttributes::SELECT(s) => ("hx-select", s),
+            HtmxAttributes::BOOST(boost) => ("hx-boost", boost),
+            HtmxAttributes::INDICATOR(s) => ("hx-indicator", s),
+            HtmxAttributes::VALS(vals) => ("hx-vals", &JsonObject(vals)),
+            HtmxAttributes::CONFIRM(s) => ("hx-confirm", s),
+        };
+        write!(f, "{}=\"", name)?;
+        write!(EscapeAttributeWriter(f), "{}", value)?;
+        f.write_char('"')
+    }
+}
+
+/// `hx-swap`: where the response goes relative to the target, then how
+#[derive(Clone, Builder)]
+#[builder(pattern = "owned")]
+#[allow(dead_code)]
+pub struct Swap {
+    #[builder(default = "SwapStyle::InnerHtml")]
+    pub style: SwapStyle,
+    #[builder(setter(each(name = "modifier")), default = "vec![]")]
+    pub modifiers: Vec<SwapModifier>,
+}
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub enum SwapStyle {
+    InnerHtml,
+    OuterHtml,
+    BeforeBegin,
+    AfterBegin,
+    BeforeEnd,
+    AfterEnd,
+    Delete,
+    None,
+}
+
+#[derive(Clone)]
+#[allow(dead_code)]
+pub enum SwapModifier {
+    /// Delay between removing the old content and adding the new
+    Swap(Duration),
+    /// Delay before attributes on the new content settle
+    Settle(Duration),
+    Scroll(ScrollTo),
+    Show(ScrollTo),
+    Transition(bool),
+    FocusScroll(bool),
+    IgnoreTitle(bool),
+}
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub enum ScrollTo {
+    Top,
+    Bottom,
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.style {
+            SwapStyle::InnerHtml => "innerHTML",
+            SwapStyle::OuterHtml => "outerHTML",
+            SwapStyle::BeforeBegin => "beforebegin",
+            SwapStyle::AfterBegin => "afterbegin",
+            SwapStyle::BeforeEnd => "beforeend",
+            SwapStyle::AfterEnd => "afterend",
+            SwapStyle::Delete => "delete",
+            SwapStyle::None => "none",
+        })?;
+        for modifier in self.modifiers.iter() {
+            match modifier {
+                SwapModifier::Swap(delay) => write!(f, " swap:{}ms", delay.as_millis()),
+                SwapModifier::Settle(delay) => write!(f, " settle:{}ms", delay.as_millis()),
+                SwapModifier::Scroll(to) => write!(f, " scroll:{}", to),
+                SwapModifier::Show(to) => write!(f, " show:{}", to),
+                SwapModifier::Transition(on) => write!(f, " transition:{}", on),
+                SwapModifier::FocusScroll(on) => write!(f, " focus-scroll:{}", on),
+                SwapModifier::IgnoreTitle(on) => write!(f, " ignoreTitle:{}", on),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ScrollTo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ScrollTo::Top => "top",
+            ScrollTo::Bottom => "bottom",
+        })
+    }
+}
+
+/// One `hx-trigger` entry: an event and its modifiers. Polling is the event `every 2s`. There are
+/// no `[filter]` expressions, htmx evaluates those with `Function()`, which the CSP blocks without
+/// `'unsafe-eval'`.
+#[derive(Clone, Builder)]
+#[builder(pattern = "owned")]
+#[allow(dead_code)]
+pub struct Trigger {
+    #[builder(setter(into))]
+    pub event: String,
+    #[builder(setter(each(name = "modifier")), default = "vec![]")]
+    pub modifiers: Vec<TriggerModifier>,
+}
+
+#[derive(Clone)]
+#[allow(dead_code)]
+pub enum TriggerModifier {
+    Once,
+    /// Only when the element's value changed
+    Changed,
+    /// Waits for the events to stop for this long
+    Delay(Duration),
+    /// At most once per this long
+    Throttle(Duration),
+    /// Listens on another element, a CSS selector or `document`/`window`
+    From(String),
+    /// Only for events on matching children
+    Target(String),
+    Consume,
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.event)?;
+        for modifier in self.modifiers.iter() {
+            match modifier {
+                TriggerModifier::Once => f.write_str(" once"),
+                TriggerModifier::Changed => f.write_str(" changed"),
+                TriggerModifier::Delay(delay) => write!(f, " delay:{}ms", delay.as_millis()),
+                TriggerModifier::Throttle(delay) => write!(f, " throttle:{}ms", delay.as_millis()),
+                TriggerModifier::From(selector) => write!(f, " from:{}", selector),
+                TriggerModifier::Target(selector) => write!(f, " target:{}", selector),
+                TriggerModifier::Consume => f.write_str(" consume"),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+/// `hx-push-url`: whether the request's url, or another one, goes into the browser history
+#[derive(Clone)]
+#[allow(dead_code)]
+pub enum PushUrl {
+    Yes,
+    No,
+    Url(String),
 }
 
-impl HtmxAttributes {
-    fn to_string(&self) -> String {
+impl fmt::Display for PushUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            HtmxAttributes::GET(s) => "hx-get=\"".to_string() + s + "\"",
-            HtmxAttributes::POST(s) => "hx-post=\"".to_string() + s + "\"",
-            HtmxAttributes::PUT(s) => "hx-put=\"".to_string() + s + "\"",
-            HtmxAttributes::PATCH(s) => "hx-patch=\"".to_string() + s + "\"",
-            HtmxAttributes::DELETE(s) => "hx-delete=\"".to_string() + s + "\"",
+            PushUrl::Yes => f.write_str("true"),
+            PushUrl::No => f.write_str("false"),
+            PushUrl::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+struct Separated<'a, T>(&'a [T], &'static str);
+
+impl<T: fmt::Display> fmt::Display for Separated<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { "" } else { self.1 }, item)?;
+        }
+        Ok(())
+    }
+}
+
+struct JsonObject<'a>(&'a [(String, String)]);
+
+impl fmt::Display for JsonObject<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('{')?;
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            write!(f, "{}{}:{}", if i == 0 { "" } else { "," }, JsonString(key), JsonString(value))?;
+        }
+        f.write_char('}')
+    }
+}
+
+struct JsonString<'a>(&'a str);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\""),
+                '\\' => f.write_str("\\\\"),
+                '\n' => f.write_str("\\n"),
+                '\r' => f.write_str("\\r"),
+                '\t' => f.write_str("\\t"),
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32),
+                c => f.write_char(c),
+            }?;
         }
+        f.write_char('"')
+    }
+}
+
+/// Text node, displayed with `<`, `>` and `&` escaped so they show up as themselves
+pub struct EscapeText<'a>(pub &'a str);
+
+impl fmt::Display for EscapeText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_escaped(f, self.0, false)
+    }
+}
+
+/// Double quoted attribute value, displayed with quotes escaped too
+pub struct EscapeAttribute<'a>(pub &'a str);
+
+impl fmt::Display for EscapeAttribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_escaped(f, self.0, true)
+    }
+}
+
+/// Escapes everything written through it as a double quoted attribute value, for values that are
+/// built with `Display` rather than held as a string
+struct EscapeAttributeWriter<'a>(&'a mut dyn Write);
+
+impl Write for EscapeAttributeWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_escaped(self.0, s, true)
+    }
+}
+
+// writes the runs between escaped characters straight through rather than building a new string
+fn write_escaped(out: &mut dyn Write, s: &str, attribute: bool) -> fmt::Result {
+    let mut written = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' if attribute => "&quot;",
+            '\'' if attribute => "&#39;",
+            _ => continue,
+        };
+        out.write_str(&s[written..i])?;
+        out.write_str(escaped)?;
+        written = i + 1;
     }
+    out.write_str(&s[written..])
 }
 
+/// Strings are text and get escaped, anything that is already html has to be wrapped in `Raw`
 pub trait IntoHtml {
     fn html_response(&self) -> Html<String> {
         Html(self.html_string())
     }
-    fn html_string(&self) -> String;
+    fn html_string(&self) -> String {
+        let mut s = String::new();
+        self.render_to(&mut s).expect("writing to a String can't fail");
+        s
+    }
+    /// Writes the html into `out`, so a whole page can render into one buffer. Takes a
+    /// `dyn Write` to keep the trait usable as `Box<dyn IntoHtml>`.
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result;
+}
+
+/// Trusted html, inserted as is. Only for markup we wrote or compiled in ourselves.
+pub struct Raw<T: AsRef<str>>(pub T);
+
+impl<T: AsRef<str>> IntoHtml for Raw<T> {
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(self.0.as_ref())
+    }
+}
+
+impl IntoHtml for Vec<Box<dyn IntoHtml>> {
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        self.iter().try_for_each(|e| e.render_to(out))
+    }
 }
 
 impl<T>  From<T> for Box<dyn IntoHtml>
@@ -96,70 +532,128 @@ where T : IntoHtml + 'static
 }
 
 impl IntoHtml for String {
-    fn html_string(&self) -> String {
-        self.clone()
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", EscapeText(self))
     }
 }
 
 impl IntoHtml for &str {
-    fn html_string(&self) -> String {
-        self.to_string()
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", EscapeText(self))
     }
 }
 
 impl IntoHtml for &&str {
-    fn html_string(&self) -> String {
-        self.to_string()
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", EscapeText(self))
     }
 }
 
-#[derive(Clone)]
-pub struct Header(pub String);
-
-impl IntoHtml for Header {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("h1", None, &None) + self.0.as_str() + "</h1>"
+impl<T: IntoHtml> IntoHtml for Option<T> {
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        match self {
+            Some(html) => html.render_to(out),
+            None => Ok(()),
+        }
     }
 }
 
-#[derive(Builder)]
-#[builder(pattern = "owned")]
-pub struct Div {
-    #[builder(setter(each(name = "element", into)), default = "vec![]")]
-    pub elements: Vec<Box<dyn IntoHtml>>,
-    #[builder(setter(into, strip_option), default)]
-    pub attributes: Option<Attributes>,
+/// Elements with content and the common `Attributes`, built like `DivBuilder::default().element(..)`.
+/// Attributes of the element's own are listed as `field: "attribute"` and left out when unset.
+macro_rules! elements {
+    ($($name:ident => $tag:literal $({ $($field:ident: $attribute:literal),* })?,)*) => {$(
+        #[derive(Builder)]
+        #[builder(pattern = "owned")]
+        pub struct $name {
+            #[builder(setter(each(name = "element", into)), default = "vec![]")]
+            pub elements: Vec<Box<dyn IntoHtml>>,
+            $($(
+            #[builder(setter(into, strip_option), default)]
+            pub $field: Option<String>,
+            )*)?
+            #[builder(setter(into, strip_option), default)]
+            pub attributes: Option<Attributes>,
+        }
+
+        impl IntoHtml for $name {
+            fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+                let own: &[(&str, Option<&str>)] = &[$($(($attribute, self.$field.as_deref())),*)?];
+                write_element(out, $tag, own, &self.attributes, |out| self.elements.iter().try_for_each(|e| e.render_to(out)))
+            }
+        }
+    )*};
 }
 
-impl IntoHtml for Div {
-    fn html_string(&self) -> String {
-        let mut s = html_element_with_attributes("div", None, &self.attributes);
-        for i in self.elements.iter() {
-            s += i.html_string().as_str();
+/// Like `elements!`, for the ones that can't have content
+macro_rules! void_elements {
+    ($($name:ident => $tag:literal { $($field:ident: $attribute:literal),* },)*) => {$(
+        #[derive(Builder)]
+        #[builder(pattern = "owned")]
+        pub struct $name {
+            $(
+            #[builder(setter(into, strip_option), default)]
+            pub $field: Option<String>,
+            )*
+            #[builder(setter(into, strip_option), default)]
+            pub attributes: Option<Attributes>,
         }
-        s += "</div>";
-        s
-    }
+
+        impl IntoHtml for $name {
+            fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+                write_element(out, $tag, &[$(($attribute, self.$field.as_deref())),*], &self.attributes, |_| Ok(()))
+            }
+        }
+    )*};
 }
 
-pub struct SimpleDiv<T:IntoHtml>(pub Option<Attributes>, pub T);
+elements! {
+    Document => "html" { lang: "lang" },
+    Head => "head",
+    Title => "title",
+    Script => "script" { src: "src", integrity: "integrity", crossorigin: "crossorigin", nonce: "nonce" },
+    Body => "body",
+    Div => "div",
+    Anchor => "a" { href: "href" },
+    Paragraph => "p",
+    Header1 => "h1",
+    Header2 => "h2",
+    Header3 => "h3",
+    Header4 => "h4",
+    Header5 => "h5",
+    Header6 => "h6",
+    Pre => "pre",
+    Code => "code",
+    Blockquote => "blockquote" { cite: "cite" },
+    Figure => "figure",
+    Figcaption => "figcaption",
+    Table => "table",
+    TableHead => "thead",
+    TableBody => "tbody",
+    TableRow => "tr",
+    TableHeaderCell => "th",
+    TableCell => "td",
+    Nav => "nav",
+    Header => "header",
+    Footer => "footer",
+    Article => "article",
+    Section => "section",
+    Time => "time" { datetime: "datetime" },
+    Form => "form" { action: "action", method: "method" },
+    Button => "button" { button_type: "type", name: "name", value: "value" },
+}
 
-impl<T:IntoHtml> IntoHtml for SimpleDiv<T> {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("div", None, &self.0) + self.1.html_string().as_str() + "</div>"
-    }
+void_elements! {
+    Meta => "meta" { name: "name", property: "property", content: "content", charset: "charset" },
+    Link => "link" { rel: "rel", href: "href", link_type: "type", title: "title", integrity: "integrity", crossorigin: "crossorigin" },
+    Input => "input" { input_type: "type", name: "name", value: "value", placeholder: "placeholder" },
 }
 
-pub struct Anchor<T: IntoHtml>(pub String, pub T);
+#[allow(dead_code)]
+pub struct SimpleDiv<T:IntoHtml>(pub Option<Attributes>, pub T);
 
-impl<T: IntoHtml> IntoHtml for Anchor<T> {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("a",
-                                     Some(vec![
-                                         format!("href=\"{}\"", self.0)]),
-                                     &None)
-            + self.1.html_string().as_str()
-            + "</a>"
+impl<T:IntoHtml> IntoHtml for SimpleDiv<T> {
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        write_element(out, "div", &[], &self.0, |out| self.1.render_to(out))
     }
 }
 
@@ -173,61 +667,47 @@ pub struct Img {
 }
 
 impl IntoHtml for Img {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("img",
-                                     Some(vec![format!("src=\"{}\"", self.uri.as_str()),
-                                               format!("alt=\"{}\"", self.alt_text.as_str())]),
-                                     &self.attributes)
-        + "</img>"
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        write_element(out, "img", &[("src", Some(&self.uri)), ("alt", Some(&self.alt_text))], &self.attributes, |_| Ok(()))
     }
 }
 
-#[derive(Builder)]
-#[builder(pattern = "owned")]
-pub struct Ulist {
-    #[builder(setter(each(name = "item", into)), default = "vec![]")]
-    pub items: Vec<Box<dyn IntoHtml>>,
-    #[builder(setter(into, strip_option), default)]
-    pub attributes: Option<Attributes>,
-    #[builder(setter(into, strip_option), default)]
-    pub item_attributes: Option<Attributes>,
-}
-
-
-// for posterity this was completely AI generated. WTF
-impl IntoHtml for Ulist{
-    fn html_string(&self) -> String {
-        let mut s = html_element_with_attributes("ul", None, &self.attributes);
-        for i in self.items.iter() {
-            s += html_element_with_attributes("li", None, &self.item_attributes).as_str();
-            s += i.html_string().as_str();
-            s += "</li>";
+/// Lists of items that each get wrapped in an `<li>` with `item_attributes`
+macro_rules! lists {
+    ($($name:ident => $tag:literal,)*) => {$(
+        #[derive(Builder)]
+        #[builder(pattern = "owned")]
+        pub struct $name {
+            #[builder(setter(each(name = "item", into)), default = "vec![]")]
+            pub items: Vec<Box<dyn IntoHtml>>,
+            #[builder(setter(into, strip_option), default)]
+            pub attributes: Option<Attributes>,
+            #[builder(setter(into, strip_option), default)]
+            pub item_attributes: Option<Attributes>,
         }
-        s + "</ul>"
-    }
-}
-
-pub struct Hr;
 
-impl IntoHtml for Hr {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("hr", None, &None) + "</hr>"
-    }
+        // for posterity this was completely AI generated. WTF
+        impl IntoHtml for $name {
+            fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+                write_element(out, $tag, &[], &self.attributes, |out| {
+                    self.items.iter().try_for_each(|i| write_element(out, "li", &[], &self.item_attributes, |out| i.render_to(out)))
+                })
+            }
+        }
+    )*};
 }
 
-pub struct Header1(pub String);
-
-impl IntoHtml for Header1 {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("h1", None, &None) + self.0.as_str() + "</h1>"
-    }
+lists! {
+    Ulist => "ul",
+    Olist => "ol",
 }
 
-pub struct Header2(pub String);
+#[allow(dead_code)]
+pub struct Hr;
 
-impl IntoHtml for Header2 {
-    fn html_string(&self) -> String {
-        html_element_with_attributes("h2", None, &None) + self.0.as_str() + "</h2>"
+impl IntoHtml for Hr {
+    fn render_to(&self, out: &mut dyn Write) -> fmt::Result {
+        write_element(out, "hr", &[], &None, |_| Ok(()))
     }
 }
 
@@ -245,22 +725,173 @@ impl std::fmt::Display for OgType {
     }
 }
 
-pub fn extract_h1(html: &str) -> Option<&str> {
-    let start = html.find("<h1>")? + 4;
-    let end = html[start..].find("</h1>")?;
-    Some(&html[start..start + end])
-}
+#[cfg(test)]
+mod test {
+    use html_macro::html;
+
+    use super::*;
+
+    #[test]
+    fn test_html_macro() {
+        let title = "<b>Tom & Jerry</b>";
+        let page = html! {
+            <div class="post wide" hx-get="/more">
+                <h2>"Posts"</h2>
+                <a href={format!("/blog/{}", "x")}>{title}</a>
+                <ul class="list"><li class="item">"one"</li><li class="item">{Raw("<i>two</i>")}</li></ul>
+                <img src="/logo.png" alt="logo" width=150 />
+                <hr />
+            </div>
+        };
+        assert_eq!(page.html_string(), "<div hx-get=\"/more\" class=\"post wide\">\
+            <h2>Posts</h2>\
+            <a href=\"/blog/x\">&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;</a>\
+            <ul class=\"list\"><li class=\"item\">one</li><li class=\"item\"><i>two</i></li></ul>\
+            <img src=\"/logo.png\" alt=\"logo\" width=\"150\">\
+            <hr>\
+            </div>");
+
+        let siblings: Vec<Box<dyn IntoHtml>> = html! { <hr /> "a & b" };
+        assert_eq!(siblings.html_string(), "<hr>a &amp; b");
+    }
 
-pub fn slug_to_title(slug: &str) -> String {
-    slug.replace(['-', '_'], " ")
-        .split_whitespace()
-        .map(|w| {
-            let mut chars = w.chars();
-            match chars.next() {
-                None => String::new(),
-                Some(f) => f.to_uppercase().collect::<String>() + chars.as_str(),
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" ")
+    #[test]
+    fn test_text_is_escaped() {
+        assert_eq!("<script>alert(1)</script>".html_string(), "&lt;script&gt;alert(1)&lt;/script&gt;");
+        assert!(html! { <h2>"Tom & Jerry <3"</h2> }.html_string().contains("Tom &amp; Jerry &lt;3"));
+        assert_eq!(Raw("<b>bold</b>").html_string(), "<b>bold</b>");
+    }
+
+    #[test]
+    fn test_void_elements() {
+        assert_eq!(Hr.html_string(), "<hr>");
+        let img = ImgBuilder::default()
+            .uri("/a.png".to_string())
+            .alt_text("a".to_string())
+            .attributes(AttributesBuilder::default()
+                .attribute(Attribute::WIDTH(10))
+                .build().unwrap())
+            .build().unwrap();
+        assert_eq!(img.html_string(), "<img src=\"/a.png\" alt=\"a\" width=\"10\">");
+
+        let head = html! {
+            <meta property="og:title" content="a \"b\"" />
+            <link rel="stylesheet" href="/base.css" />
+            <input type="text" name="q" />
+        };
+        assert_eq!(head.html_string(), "<meta property=\"og:title\" content=\"a &quot;b&quot;\">\
+            <link rel=\"stylesheet\" href=\"/base.css\">\
+            <input type=\"text\" name=\"q\">");
+    }
+
+    #[test]
+    fn test_non_void_elements() {
+        assert_eq!(Header2Builder::default().element("a").build().unwrap().html_string(), "<h2>a</h2>");
+        assert_eq!(html! { <a href="/">"home"</a> }.html_string(), "<a href=\"/\">home</a>");
+        let div = DivBuilder::default()
+            .element(UlistBuilder::default().item("x").build().unwrap())
+            .attributes(AttributesBuilder::default()
+                .attribute(Attribute::CLASS(vec!["a".to_string(), "b".to_string()]))
+                .build().unwrap())
+            .build().unwrap();
+        assert_eq!(div.html_string(), "<div class=\"a b\"><ul><li>x</li></ul></div>");
+
+        let article = html! {
+            <article class="post">
+                <header><h4>"Title"</h4><time datetime="2024-01-02">"Jan 2"</time></header>
+                <section>
+                    <p>"Steps:"</p>
+                    <ol><li>"one"</li><li>"two"</li></ol>
+                    <pre><code class="language-rust">"let x = 1 < 2;"</code></pre>
+                    <blockquote cite="https://example.com"><p>"quoted"</p></blockquote>
+                    <figure><img src="/a.png" alt="a" /><figcaption>"caption"</figcaption></figure>
+                    <table>
+                        <thead><tr><th>"k"</th><th>"v"</th></tr></thead>
+                        <tbody><tr><td>"a"</td><td>"1"</td></tr></tbody>
+                    </table>
+                </section>
+                <form action="/search" method="get"><button type="submit">"Go"</button></form>
+                <footer><nav><h5>"more"</h5><h6>"less"</h6></nav></footer>
+            </article>
+        };
+        assert_eq!(article.html_string(), "<article class=\"post\">\
+            <header><h4>Title</h4><time datetime=\"2024-01-02\">Jan 2</time></header>\
+            <section>\
+            <p>Steps:</p>\
+            <ol><li>one</li><li>two</li></ol>\
+            <pre><code class=\"language-rust\">let x = 1 &lt; 2;</code></pre>\
+            <blockquote cite=\"https://example.com\"><p>quoted</p></blockquote>\
+            <figure><img src=\"/a.png\" alt=\"a\"><figcaption>caption</figcaption></figure>\
+            <table><thead><tr><th>k</th><th>v</th></tr></thead><tbody><tr><td>a</td><td>1</td></tr></tbody></table>\
+            </section>\
+            <form action=\"/search\" method=\"get\"><button type=\"submit\">Go</button></form>\
+            <footer><nav><h5>more</h5><h6>less</h6></nav></footer>\
+            </article>");
+    }
+
+    #[test]
+    fn test_attributes_are_escaped() {
+        let anchor = html! { <a href="/x\" onclick=\"alert(1)">"<i>x</i>"</a> }.html_string();
+        assert!(anchor.contains("href=\"/x&quot; onclick=&quot;alert(1)\""));
+        assert!(anchor.contains("&lt;i&gt;x&lt;/i&gt;"));
+
+        let img = ImgBuilder::default()
+            .uri("/a.png".to_string())
+            .alt_text("' onerror='alert(1)".to_string())
+            .attributes(AttributesBuilder::default()
+                .attribute(Attribute::CLASS(vec!["a\"><script>".to_string()]))
+                .htmx_attribute(HtmxAttributes::GET("/x\"".to_string()))
+                .build().unwrap())
+            .build().unwrap()
+            .html_string();
+        assert!(img.contains("alt=\"&#39; onerror=&#39;alert(1)\""));
+        assert!(img.contains("class=\"a&quot;&gt;&lt;script&gt;\""));
+        assert!(img.contains("hx-get=\"/x&quot;\""));
+        assert!(!img.contains("<script>"));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let style = StyleBuilder::default()
+            .declaration(Declaration::Margin(Length::Em(1)))
+            .declaration(Declaration::Width(Length::Vw(50)))
+            .build().unwrap();
+        let nav = html! {
+            <nav id="sections" role="navigation" aria-label="Sections \"main\"" data-section-count={4} data-postId="7" style={style} title="a & b" lang="en">
+                <a href="/">"Home"</a>
+            </nav>
+        };
+        assert_eq!(nav.html_string(), "<nav id=\"sections\" role=\"navigation\" aria-label=\"Sections &quot;main&quot;\" \
+            data-section-count=\"4\" data-postid=\"7\" style=\"margin: 1em; width: 50vw;\" title=\"a &amp; b\" lang=\"en\">\
+            <a href=\"/\">Home</a></nav>");
+
+        assert_eq!(PrefixedName::new("postId"), Some(PrefixedName("postid".to_string())));
+        assert_eq!(PrefixedName::new("x\" onclick"), None);
+        assert_eq!(PrefixedName::new(""), None);
+    }
+
+    #[test]
+    fn test_htmx_attributes() {
+        let swap = SwapBuilder::default()
+            .style(SwapStyle::OuterHtml)
+            .modifier(SwapModifier::Swap(Duration::from_secs(1)))
+            .modifier(SwapModifier::Show(ScrollTo::Top))
+            .build().unwrap();
+        let search = TriggerBuilder::default()
+            .event("keyup")
+            .modifier(TriggerModifier::Changed)
+            .modifier(TriggerModifier::Delay(Duration::from_millis(500)))
+            .build().unwrap();
+        let load = TriggerBuilder::default().event("load").build().unwrap();
+        let div = html! {
+            <div hx-get="/search" hx-target="#results" hx-swap={swap} hx-trigger={vec![search, load]}
+                 hx-push-url={PushUrl::Yes} hx-select="#results" hx-boost={true} hx-indicator=".spinner"
+                 hx-vals={vec![("q".to_string(), "say \"hi\"".to_string())]} hx-confirm="Sure?"></div>
+        };
+        assert_eq!(div.html_string(), "<div hx-get=\"/search\" hx-target=\"#results\" \
+            hx-swap=\"outerHTML swap:1000ms show:top\" \
+            hx-trigger=\"keyup changed delay:500ms, load\" \
+            hx-push-url=\"true\" hx-select=\"#results\" hx-boost=\"true\" hx-indicator=\".spinner\" \
+            hx-vals=\"{&quot;q&quot;:&quot;say \\&quot;hi\\&quot;&quot;}\" hx-confirm=\"Sure?\"></div>");
+    }
 }
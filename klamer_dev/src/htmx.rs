@@ -0,0 +1,142 @@
+use std::convert::Infallible;
+
+use axum::extract::OptionalFromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponseParts, ResponseParts};
+
+const HX_REQUEST: HeaderName = HeaderName::from_static("hx-request");
+const HX_BOOSTED: HeaderName = HeaderName::from_static("hx-boosted");
+const HX_TARGET: HeaderName = HeaderName::from_static("hx-target");
+const HX_CURRENT_URL: HeaderName = HeaderName::from_static("hx-current-url");
+
+/// A request made by htmx, extracted as `Option<HxRequest>` so handlers can answer it with just
+/// the fragment that gets swapped in instead of the whole page.
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub struct HxRequest {
+    /// From an `hx-boost`ed link or form, which swaps the whole body and so wants the full page
+    pub boosted: bool,
+    /// `id` of the target element, if it has one
+    pub target: Option<String>,
+    pub current_url: Option<String>,
+}
+
+impl HxRequest {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        if headers.get(HX_REQUEST)? != "true" {
+            return None;
+        }
+        let header = |name| headers.get(name).and_then(|v: &HeaderValue| v.to_str().ok()).map(str::to_string);
+        Some(HxRequest {
+            boosted: headers.get(HX_BOOSTED).is_some_and(|v| v == "true"),
+            target: header(HX_TARGET),
+            current_url: header(HX_CURRENT_URL),
+        })
+    }
+
+    /// Whether the response only needs the part of the page being swapped
+    pub fn wants_fragment(&self) -> bool {
+        !self.boosted
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for HxRequest {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Option<Self>, Self::Rejection> {
+        Ok(HxRequest::from_headers(&parts.headers))
+    }
+}
+
+/// Makes htmx load the url as a full page instead of swapping the response in
+#[allow(dead_code)]
+pub struct HxRedirect(pub String);
+
+impl IntoResponseParts for HxRedirect {
+    type Error = StatusCode;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let location = HeaderValue::from_str(&self.0).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        res.headers_mut().insert(HeaderName::from_static("hx-redirect"), location);
+        Ok(res)
+    }
+}
+
+/// Client side events htmx triggers once the response arrives, for other elements to `hx-trigger` on
+#[allow(dead_code)]
+pub struct HxTrigger(pub Vec<String>);
+
+impl IntoResponseParts for HxTrigger {
+    type Error = StatusCode;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let events = HeaderValue::from_str(&self.0.join(", ")).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        res.headers_mut().insert(HeaderName::from_static("hx-trigger"), events);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::cache;
+
+    async fn fragment_or_page(hx: Option<HxRequest>) -> &'static str {
+        match hx {
+            Some(hx) if hx.wants_fragment() => "fragment",
+            _ => "page",
+        }
+    }
+
+    async fn get_body(router: &Router, headers: &[(&str, &str)]) -> String {
+        let mut request = Request::get("/htmx_test");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        // the same url has two bodies, so caches outside the server have to keep them apart too
+        assert!(response.headers().get_all(header::VARY).iter().any(|vary| vary == "hx-request"), "{:?}", response.headers());
+        String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_hx_request() {
+        assert!(HxRequest::from_headers(&HeaderMap::new()).is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(HX_REQUEST, HeaderValue::from_static("true"));
+        headers.insert(HX_TARGET, HeaderValue::from_static("posts"));
+        let hx = HxRequest::from_headers(&headers).unwrap();
+        assert!(hx.wants_fragment());
+        assert_eq!(hx.target.as_deref(), Some("posts"));
+
+        headers.insert(HX_BOOSTED, HeaderValue::from_static("true"));
+        assert!(!HxRequest::from_headers(&headers).unwrap().wants_fragment());
+    }
+
+    #[tokio::test]
+    async fn test_fragments_are_cached_apart() {
+        let router = Router::new()
+            .route("/htmx_test", get(fragment_or_page))
+            .layer(middleware::from_fn(cache::conditional_get));
+        assert_eq!(get_body(&router, &[]).await, "page");
+        assert_eq!(get_body(&router, &[("hx-request", "true")]).await, "fragment");
+        assert_eq!(get_body(&router, &[("hx-request", "true"), ("hx-boosted", "true")]).await, "page");
+        assert_eq!(get_body(&router, &[]).await, "page");
+    }
+
+    #[test]
+    fn test_response_headers() {
+        let response = (HxRedirect("/blog".to_string()), HxTrigger(vec!["posts-changed".to_string(), "saved".to_string()]), "").into_response();
+        assert_eq!(response.headers()["hx-redirect"], "/blog");
+        assert_eq!(response.headers()["hx-trigger"], "posts-changed, saved");
+    }
+}
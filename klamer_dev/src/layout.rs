@@ -0,0 +1,278 @@
+use axum::response::Html;
+use derive_builder::Builder;
+use html_macro::html;
+
+use crate::html::Attribute::CLASS;
+use crate::html::{Anchor, AttributesBuilder, Footer, IntoHtml, OgType, UlistBuilder};
+use crate::{assets, SITE_URL};
+
+pub const SITE_NAME: &str = "Klamer.dev";
+// rendering buffer, big enough that most pages never have to grow it
+pub const PAGE_CAPACITY: usize = 32 * 1024;
+
+/// The header's sections, in order
+pub const NAVIGATION: &[NavItem] = &[
+    NavItem { label: "Home", path: "/", match_prefix: None },
+    NavItem { label: "Blog", path: "/blog", match_prefix: Some("/blog/") },
+    NavItem { label: "Reads", path: "/good_reads", match_prefix: None },
+    NavItem { label: "Models", path: "/models", match_prefix: None },
+];
+
+/// A section in the header, current for its own path and anything starting with `match_prefix`
+pub struct NavItem {
+    pub label: &'static str,
+    pub path: &'static str,
+    pub match_prefix: Option<&'static str>,
+}
+
+impl NavItem {
+    fn is_current(&self, path: &str) -> bool {
+        path == self.path || self.match_prefix.is_some_and(|prefix| path.starts_with(prefix))
+    }
+
+    fn link(&self, current_path: Option<&str>) -> Anchor {
+        if current_path.is_some_and(|path| self.is_current(path)) {
+            html! { <a href={self.path} class="active" aria-current="page">{self.label}</a> }
+        } else {
+            html! { <a href={self.path}>{self.label}</a> }
+        }
+    }
+}
+
+/// Picture for link previews
+#[derive(Clone)]
+pub struct OgImage {
+    /// Absolute, crawlers don't resolve relative urls
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub alt: String,
+}
+
+impl OgImage {
+    /// For pages without a picture of their own
+    pub fn logo() -> Self {
+        OgImage {
+            url: format!("{}{}", SITE_URL, assets::LOGO.fingerprinted_path),
+            width: 590,
+            height: 299,
+            alt: "Klamer.dev logo".to_string(),
+        }
+    }
+
+    /// Big, wide pictures get the card that shows them full width
+    fn twitter_card(&self) -> &'static str {
+        if self.width >= 600 && self.width * 2 >= self.height * 3 { "summary_large_image" } else { "summary" }
+    }
+}
+
+/// What a page declares about itself, everything in `<head>` and around its content
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct PageMeta {
+    /// Goes in the tab as "title | Klamer.dev" and is the `og:title`
+    #[builder(setter(into))]
+    title: String,
+    #[builder(setter(into))]
+    description: String,
+    /// Where the page is served, for the canonical url and to highlight its section in the header
+    #[builder(setter(into, strip_option), default)]
+    path: Option<String>,
+    #[builder(default = "NAVIGATION")]
+    navigation: &'static [NavItem],
+    /// Defaults to the site url plus `path`
+    #[builder(setter(into, strip_option), default)]
+    canonical: Option<String>,
+    #[builder(default = "OgType::Website")]
+    og_type: OgType,
+    #[builder(default = "OgImage::logo()")]
+    og_image: OgImage,
+    /// `article:published_time`, RFC 3339
+    #[builder(setter(into, strip_option), default)]
+    published_time: Option<String>,
+    /// `article:tag`s
+    #[builder(setter(each(name = "tag", into)), default = "vec![]")]
+    tags: Vec<String>,
+    /// Extra `<head>` elements, like stylesheets only some pages need
+    #[builder(setter(each(name = "head_element", into)), default = "vec![]")]
+    head: Vec<Box<dyn IntoHtml>>,
+    /// Scripts at the end of `<body>`
+    #[builder(setter(each(name = "script", into)), default = "vec![]")]
+    scripts: Vec<Box<dyn IntoHtml>>,
+    #[builder(setter(into), default = "\"en\".to_string()")]
+    lang: String,
+    #[builder(default = "true")]
+    footer: bool,
+}
+
+impl PageMeta {
+    pub fn page(self, content: Vec<Box<dyn IntoHtml>>) -> Html<String> {
+        let title = if self.title == SITE_NAME { self.title.clone() } else { format!("{} | {}", self.title, SITE_NAME) };
+        let canonical = self.canonical.or_else(|| self.path.as_ref().map(|path| format!("{}{}", SITE_URL, path)));
+        let tags: Vec<Box<dyn IntoHtml>> = self.tags.iter().map(|tag| html! { <meta property="article:tag" content={tag} /> }.into()).collect();
+        let mut sections = UlistBuilder::default()
+            .attributes(AttributesBuilder::default()
+                .attribute(CLASS(vec!["section-items".to_string()]))
+                .build().unwrap())
+            .item_attributes(AttributesBuilder::default()
+                .attribute(CLASS(vec!["section-item".to_string()]))
+                .build().unwrap());
+        for item in self.navigation {
+            sections = sections.item(item.link(self.path.as_deref()));
+        }
+
+        let document = html! {
+            <html lang={self.lang}>
+                <head>
+                    <title>{title}</title>
+                    <meta name="description" content={self.description} />
+                    {canonical.map(|canonical| html! {
+                        <link rel="canonical" href={canonical} />
+                        <meta property="og:url" content={canonical} />
+                    })}
+                    <link rel="icon" type="image/png" href={assets::FAVICON.fingerprinted_path} />
+                    {assets::BASE_CSS.stylesheet()}
+                    <link rel="alternate" type="application/atom+xml" title={SITE_NAME} href="/feed.xml" />
+                    <link rel="alternate" type="application/rss+xml" title={SITE_NAME} href="/rss.xml" />
+                    <meta property="og:title" content={self.title} />
+                    <meta property="og:description" content={self.description} />
+                    <meta property="og:type" content={self.og_type} />
+                    <meta property="og:site_name" content={SITE_NAME} />
+                    <meta property="og:image" content={self.og_image.url} />
+                    <meta property="og:image:width" content={self.og_image.width} />
+                    <meta property="og:image:height" content={self.og_image.height} />
+                    <meta property="og:image:alt" content={self.og_image.alt} />
+                    {self.published_time.map(|time| html! { <meta property="article:published_time" content={time} /> })}
+                    {tags}
+                    <meta name="twitter:card" content={self.og_image.twitter_card()} />
+                    {self.head}
+                    // htmx would otherwise inject a <style> for indicators, which the CSP blocks
+                    <meta name="htmx-config" content="{\"includeIndicatorStyles\":false}" />
+                    {assets::HTMX.script()}
+                </head>
+                <body>
+                    <div class="center">
+                        <div class="Container">
+                            <div class="Logo">
+                                <a href="/"><img src={assets::LOGO.fingerprinted_path} alt="Klamer.dev logo" width=150 /></a>
+                            </div>
+                            <nav class="Sections" aria-label="Sections">{sections.build().unwrap()}</nav>
+                        </div>
+                        <div class="Content">{content}</div>
+                        {self.footer.then(footer)}
+                    </div>
+                    {self.scripts}
+                </body>
+            </html>
+        };
+
+        let mut html = String::with_capacity(PAGE_CAPACITY);
+        html += "<!DOCTYPE html>";
+        document.render_to(&mut html).unwrap();
+        Html(html)
+    }
+}
+
+fn footer() -> Footer {
+    html! {
+        <footer>
+            "©2026 Jack Klamer"
+            <p>"Source: "<a href="https://github.com/jklamer/klamer.dev">"https://github.com/jklamer/klamer.dev"</a></p>
+        </footer>
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn meta(title: &str) -> PageMetaBuilder {
+        PageMetaBuilder::default().title(title).description("a & b")
+    }
+
+    #[test]
+    fn test_title() {
+        let Html(home) = meta(SITE_NAME).build().unwrap().page(vec![]);
+        assert!(home.contains("<title>Klamer.dev</title>"));
+        let Html(post) = meta("Jersey <numbers>").build().unwrap().page(vec![]);
+        assert!(post.contains("<title>Jersey &lt;numbers&gt; | Klamer.dev</title>"));
+        assert!(post.contains("<meta property=\"og:title\" content=\"Jersey &lt;numbers&gt;\">"));
+        assert!(post.contains("<meta name=\"description\" content=\"a &amp; b\">"));
+    }
+
+    #[test]
+    fn test_meta() {
+        let Html(page) = meta("Post")
+            .path("/blog/post")
+            .og_type(OgType::Article)
+            .og_image(OgImage { url: "https://klamer.dev/og.png".to_string(), width: 1200, height: 630, alt: "Post".to_string() })
+            .published_time("2024-01-02T00:00:00Z")
+            .tag("rust")
+            .tag("a \"b\"")
+            .head_element(assets::PRISM_CSS.stylesheet())
+            .script(html! { <script src="/x.js"></script> })
+            .lang("en-US")
+            .footer(false)
+            .build().unwrap()
+            .page(vec!["content".into()]);
+        assert!(page.starts_with("<!DOCTYPE html><html lang=\"en-US\"><head>"));
+        assert!(page.contains("<link rel=\"canonical\" href=\"https://klamer.dev/blog/post\">"));
+        assert!(page.contains("<meta property=\"og:type\" content=\"article\">"));
+        assert!(page.contains("<meta property=\"og:url\" content=\"https://klamer.dev/blog/post\">"));
+        assert!(page.contains("<meta property=\"og:site_name\" content=\"Klamer.dev\">"));
+        assert!(page.contains("<meta property=\"og:image\" content=\"https://klamer.dev/og.png\">\
+            <meta property=\"og:image:width\" content=\"1200\"><meta property=\"og:image:height\" content=\"630\">"));
+        assert!(page.contains("<meta property=\"article:published_time\" content=\"2024-01-02T00:00:00Z\">"));
+        assert!(page.contains("<meta property=\"article:tag\" content=\"rust\"><meta property=\"article:tag\" content=\"a &quot;b&quot;\">"));
+        assert!(page.contains("<meta name=\"twitter:card\" content=\"summary_large_image\">"));
+        assert!(page.contains(assets::PRISM_CSS.fingerprinted_path));
+        assert!(page.ends_with("<script src=\"/x.js\"></script></body></html>"));
+        assert!(!page.contains("<footer>"));
+
+        let Html(not_found) = meta("Not found").build().unwrap().page(vec![]);
+        assert!(!not_found.contains("rel=\"canonical\""));
+        assert!(!not_found.contains("og:url"));
+        assert!(!not_found.contains("article:"));
+        assert!(not_found.contains(&format!("<meta property=\"og:image\" content=\"https://klamer.dev{}\">", assets::LOGO.fingerprinted_path)));
+        assert!(not_found.contains("<meta name=\"twitter:card\" content=\"summary\">"));
+        assert!(not_found.contains("<footer>"));
+    }
+
+    #[test]
+    fn test_logo_size() {
+        // the PNG header's IHDR chunk: width then height, big endian
+        let size = |offset: usize| u32::from_be_bytes(assets::LOGO.bytes[offset..offset + 4].try_into().unwrap());
+        let logo = OgImage::logo();
+        assert_eq!((size(16), size(20)), (logo.width, logo.height));
+    }
+
+    #[test]
+    fn test_active_section() {
+        let Html(post) = meta("Post").path("/blog/post").build().unwrap().page(vec![]);
+        assert!(post.contains("<a href=\"/blog\" class=\"active\" aria-current=\"page\">Blog</a>"));
+        assert!(post.contains("<a href=\"/\">Home</a>"));
+        let Html(home) = meta(SITE_NAME).path("/").build().unwrap().page(vec![]);
+        assert!(home.contains("<a href=\"/\" class=\"active\" aria-current=\"page\">Home</a>"));
+        assert!(home.contains("<a href=\"/blog\">Blog</a>"));
+        let Html(not_found) = meta("Not found").build().unwrap().page(vec![]);
+        assert!(!not_found.contains("aria-current"));
+    }
+
+    #[test]
+    fn test_navigation() {
+        const SECTIONS: &[NavItem] = &[
+            NavItem { label: "Docs", path: "/docs", match_prefix: Some("/docs/") },
+            NavItem { label: "Docs index", path: "/docs/index", match_prefix: None },
+        ];
+        let Html(page) = meta("Docs").path("/docs/index").navigation(SECTIONS).build().unwrap().page(vec![]);
+        assert!(page.contains("<nav class=\"Sections\" aria-label=\"Sections\"><ul class=\"section-items\">\
+            <li class=\"section-item\"><a href=\"/docs\" class=\"active\" aria-current=\"page\">Docs</a></li>\
+            <li class=\"section-item\"><a href=\"/docs/index\" class=\"active\" aria-current=\"page\">Docs index</a></li>\
+            </ul></nav>"));
+        assert!(!page.contains("Models"));
+
+        assert!(NAVIGATION[1].is_current("/blog/archive"));
+        assert!(!NAVIGATION[1].is_current("/blogroll"));
+        assert!(!NAVIGATION[0].is_current("/blog"));
+    }
+}
@@ -1,7 +1,3 @@
-#[macro_use]
-extern crate lazy_static;
-
-use std::collections::HashMap;
 use std::future::Future;
 use std::iter::Iterator;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
@@ -11,12 +7,13 @@ use std::time::Duration;
 use axum::extract::{Path, State};
 use axum::http::{header, HeaderValue, StatusCode, Uri};
 use axum::response::{Html, IntoResponse, Redirect};
-use axum::routing::get;
-use axum::{BoxError, Router};
+use axum::routing::{get, MethodRouter};
+use axum::{middleware, BoxError, Extension, Router};
 use axum_extra::extract::Host;
 use axum_server::Handle;
 use clap::Parser;
 use futures::StreamExt;
+use html_macro::html;
 use rustls_acme::UseChallenge::Http01;
 use rustls_acme::{AcmeConfig, ResolvesServerCertAcme};
 use tokio::signal;
@@ -24,35 +21,51 @@ use tokio::signal::ctrl_c;
 use tower_governor::governor::GovernorConfigBuilder;
 use tower_governor::GovernorLayer;
 use tower_http::trace::TraceLayer;
-use tracing_subscriber;
 
-use blog_files_macro::list_blog_files;
 use rustls_acme_cache::{AcmeS3Cache, NoAccountAcmeS3Cache};
 
-use crate::html::Attribute::{WidthVw, CLASS};
-use crate::html::{Anchor, AttributesBuilder, DivBuilder, Header2, ImgBuilder, IntoHtml, OgType, UlistBuilder, extract_h1, slug_to_title};
-
+use crate::html::Attribute::CLASS;
+use crate::blog::BlogPost;
+use crate::cache::ContentHash;
+use crate::html::{AttributesBuilder, Div, IntoHtml, OgType, Raw, UlistBuilder};
+use crate::layout::{OgImage, PageMetaBuilder, SITE_NAME};
+use crate::security::{SecurityHeaders, SecurityHeadersBuilder};
+
+mod assets;
+mod blog;
+mod cache;
+mod encoding;
+mod feed;
 mod html;
+mod htmx;
+mod layout;
+mod og_image;
+mod security;
+mod sitemap;
+
+pub const SITE_URL: &str = "https://klamer.dev";
+
+/// A page at a fixed path. `app()` routes every one of these, and the sitemap lists them alongside
+/// the posts unless they're `unlisted`.
+struct StaticPage {
+    path: &'static str,
+    route: fn() -> MethodRouter,
+    unlisted: bool,
+}
+
+const STATIC_PAGES: &[StaticPage] = &[
+    StaticPage { path: "/", route: || get(home_page), unlisted: false },
+    StaticPage { path: "/blog", route: || get(blog_page), unlisted: false },
+    StaticPage { path: "/blog/archive", route: || get(blog_archive), unlisted: false },
+    StaticPage { path: "/good_reads", route: || get(good_reads_page), unlisted: false },
+    StaticPage { path: "/models", route: || get(models_page), unlisted: false },
+    StaticPage { path: "/annie", route: || get(annie_page), unlisted: true },
+];
 
-const ICON: &[u8] = include_bytes!("../assets/k_logo.dev.png");
-const LOGO: &[u8] = include_bytes!("../assets/klamer.dev.png");
-const BASE_CSS: &str = include_str!("../css/base.css");
-const PRISM_CSS: &str = include_str!("../css/prism-gruvbox-dark.css");
 const HOME: &str = include_str!("../assets/home.html");
 const GOOD_READS: &str = include_str!("../assets/good_reads.html");
 const MODELS: &str = include_str!("../assets/models.html");
 const FOUR04: &str = include_str!("../assets/404.html");
-const BLOG_POST_CONTENT: &[(&'static str, &'static str)] = &list_blog_files!();
-
-lazy_static! {
-    static ref POST_NAMES: Vec<&'static str> = BLOG_POST_CONTENT.iter().map(|b| get_post_name(b.0)).collect();
-    static ref POSTS: HashMap<&'static str, &'static str> = BLOG_POST_CONTENT.iter().map(|b| (get_post_name(b.0), b.1)).collect();
-    static ref POST_TITLES: HashMap<&'static str, String> = BLOG_POST_CONTENT.iter().map(|b| {
-        let name = get_post_name(b.0);
-        let title = extract_h1(b.1).map(|t| t.to_string()).unwrap_or_else(|| slug_to_title(name));
-        (name, title)
-    }).collect();
-}
 
 #[derive(Parser, Debug)]
 struct TlsArgs {
@@ -108,18 +121,15 @@ async fn main() {
     });
 
 
-    let app = Router::new()
-        .route("/", get(home_page))
-        .route("/blog", get(blog_page))
-        .route("/good_reads", get(good_reads_page))
-        .route("/models", get(models_page))
-        .route("/blog/{post_name}", get(blog_post))
-        .route("/annie", get(annie_page))
-        .route("/favicon.png", get(icon))
-        .route("/logo.png", get(logo))
-        .route("/base.css", get(base_css))
-        .route("/prism.css", get(prism_css))
-        .fallback(four04)
+    // HSTS only makes sense, and is only honoured, over TLS
+    let security_headers = if deployed_env {
+        SecurityHeadersBuilder::default().hsts(Duration::from_secs(63072000)).build().unwrap()
+    } else {
+        SecurityHeaders::default()
+    };
+    // render and compress everything before the first reader asks for it
+    tokio::spawn(cache::warm(app(), cached_paths()));
+    let app = security_headers.layer(app())
         .layer(GovernorLayer{ config: governor_conf })
         .layer(TraceLayer::new_for_http());
 
@@ -175,6 +185,19 @@ async fn main() {
     }
 }
 
+fn app() -> Router {
+    STATIC_PAGES.iter().fold(Router::new(), |router, page| router.route(page.path, (page.route)()))
+        .route("/blog/{post_name}", get(blog_post))
+        .route("/blog/{post_name}/og.png", get(blog_post_og_image))
+        .route("/feed.xml", get(atom_feed))
+        .route("/rss.xml", get(rss_feed))
+        .route("/sitemap.xml", get(sitemap_xml))
+        .route("/robots.txt", get(robots_txt))
+        .merge(assets::router())
+        .fallback(four04)
+        .layer(middleware::from_fn(cache::conditional_get))
+}
+
 async fn shutdown_signal(handle: Option<Handle>) {
     let ctrl_c = async {
         ctrl_c()
@@ -253,20 +276,32 @@ fn make_https(host: String, uri: Uri, https_port: u16, http_port: u16) -> Result
     Ok(Uri::from_parts(parts)?)
 }
 
-fn get_post_name(file_name: &str) -> &str {
-    std::path::Path::new(file_name).file_name().unwrap().to_str().unwrap().trim_end_matches(".html")
-}
-
 async fn home_page() -> Html<String> {
-    page(vec![HOME.into()], false, false, "Klamer.dev", "Idiot website speedrun", OgType::Website)
+    PageMetaBuilder::default()
+        .title(SITE_NAME)
+        .description("Idiot website speedrun")
+        .path("/")
+        .footer(false)
+        .build().unwrap()
+        .page(vec![Raw(HOME).into()])
 }
 
 async fn good_reads_page() -> Html<String> {
-    page(vec![GOOD_READS.into()], true, false, "Klamer.dev", "Things to read", OgType::Website)
+    PageMetaBuilder::default()
+        .title("Good reads")
+        .description("Things to read")
+        .path("/good_reads")
+        .build().unwrap()
+        .page(vec![Raw(GOOD_READS).into()])
 }
 
 async fn models_page() -> Html<String> {
-    page(vec![MODELS.into()], true, false, "Klamer.dev", "Models For Thinking", OgType::Website)
+    PageMetaBuilder::default()
+        .title("Models")
+        .description("Models For Thinking")
+        .path("/models")
+        .build().unwrap()
+        .page(vec![Raw(MODELS).into()])
 }
 
 // write axum handlers needed to set up a blog
@@ -275,141 +310,374 @@ async fn blog_page() -> Html<String> {
         .item_attributes(AttributesBuilder::default()
             .attribute(CLASS(vec!["post-list".to_string()]))
             .build().unwrap());
-    for post_name in POST_NAMES.iter() {
-        post_list_builder = post_list_builder.item(Anchor(format!("/blog/{post_name}"), *post_name))
+    for post in blog::posts() {
+        post_list_builder = post_list_builder.item(html! {
+            <div>
+                <a href={format!("/blog/{}", post.slug)}>{post.title}</a>
+                {post_date(post)}
+                <div class="post-summary">{post.summary}</div>
+            </div>
+        })
     }
 
-    page(vec![Header2("Posts".to_string()).into(), post_list_builder.build().unwrap().into()], true, false, "Klamer.dev", "Posts & writing", OgType::Website)
+    PageMetaBuilder::default()
+        .title("Blog")
+        .description("Posts & writing")
+        .path("/blog")
+        .build().unwrap()
+        .page(html! {
+            <h2>"Posts"</h2>
+            {post_list_builder.build().unwrap()}
+            <a href="/blog/archive">"Archive"</a>
+        })
 }
 
-async fn blog_post(Path(post_name): Path<String>) -> Html<String> {
-    let title = POST_TITLES.get(post_name.as_str()).map(|s| s.as_str()).unwrap_or(post_name.as_str());
-    page(vec![POSTS.get(post_name.as_str()).unwrap_or(&FOUR04).into()], true, true, title, "klamer.dev", OgType::Article)
+async fn blog_archive() -> Html<String> {
+    let mut content: Vec<Box<dyn IntoHtml>> = vec![html! { <h2>"Archive"</h2> }.into()];
+    let posts = blog::posts().collect::<Vec<_>>();
+    for year in posts.chunk_by(|a, b| a.published.year == b.published.year) {
+        content.push(html! { <h3>{year[0].published.year.to_string()}</h3> }.into());
+        for month in year.chunk_by(|a, b| a.published.month == b.published.month) {
+            let mut post_list_builder = UlistBuilder::default()
+                .item_attributes(AttributesBuilder::default()
+                    .attribute(CLASS(vec!["post-list".to_string()]))
+                    .build().unwrap());
+            for post in month {
+                post_list_builder = post_list_builder.item(html! {
+                    <div>
+                        <a href={format!("/blog/{}", post.slug)}>{post.title}</a>
+                        {post_date(post)}
+                    </div>
+                })
+            }
+            content.push(html! {
+                <div>
+                    <div class="archive-month">{month[0].published.month_name()}</div>
+                    {post_list_builder.build().unwrap()}
+                </div>
+            }.into());
+        }
+    }
+
+    PageMetaBuilder::default()
+        .title("Archive")
+        .description("Posts by year & month")
+        .path("/blog/archive")
+        .build().unwrap()
+        .page(content)
 }
 
-async fn annie_page() -> Html<String> {
-    page(vec!["She's the best".into()], true, false, "Klamer.dev", "klamer.dev", OgType::Website)
+fn post_date(post: &BlogPost) -> Div {
+    html! { <div class="post-date">{post.published.to_string()}</div> }
 }
 
-async fn four04() -> Html<String> {
-    page(vec![FOUR04.into()], false, false, "Klamer.dev", "klamer.dev", OgType::Website)
+async fn blog_post(Path(post_name): Path<String>) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    match blog::find_post(&post_name) {
+        Some(post) => Ok((Extension(ContentHash(post.content_hash)), PageMetaBuilder::default()
+            .title(post.title)
+            .description(post.summary)
+            .path(format!("/blog/{}", post.slug))
+            .og_type(OgType::Article)
+            .og_image(OgImage {
+                url: format!("{}/blog/{}/og.png", SITE_URL, post.slug),
+                width: og_image::WIDTH,
+                height: og_image::HEIGHT,
+                alt: post.title.to_string(),
+            })
+            .published_time(post.published.rfc3339())
+            .tags(post.tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>())
+            .head_element(assets::PRISM_CSS.stylesheet())
+            .build().unwrap()
+            .page(vec![Raw(post.content).into()]))),
+        None => Err(four04().await),
+    }
 }
 
-fn page(content: Vec<Box<dyn IntoHtml>>, include_footer: bool, include_prism: bool, og_title: &str, og_description: &str, og_type: OgType) -> Html<String> {
-    let top_nav: Vec<Box<dyn IntoHtml>> = vec![
-        Box::new(DivBuilder::default()
-            .element(Anchor("/".to_string(), ImgBuilder::default()
-                .uri("/logo.png".to_string())
-                .alt_text("Klamer.dev logo".to_string())
-                .attributes(AttributesBuilder::default()
-                    .attribute(WidthVw(150))
-                    .build().unwrap())
-                .build().unwrap()))
-            .attributes(AttributesBuilder::default()
-                .attribute(CLASS(vec!["Logo".to_string()]))
-                .build().unwrap())
-            .build().unwrap()),
-        Box::new(
-            DivBuilder::default()
-                .element(UlistBuilder::default()
-                    .item(Anchor("/".to_string(), "Home"))
-                    .item(Anchor("/blog".to_string(), "Blog"))
-                    .item(Anchor("/good_reads".to_string(), "Reads"))
-                    .item(Anchor("/models".to_string(), "Models"))
-                    .attributes(AttributesBuilder::default()
-                        .attribute(CLASS(vec!["section-items".to_string()]))
-                        .build().unwrap())
-                    .item_attributes(AttributesBuilder::default()
-                        .attribute(CLASS(vec!["section-item".to_string()]))
-                        .build().unwrap())
-                    .build().unwrap())
-                .attributes(AttributesBuilder::default()
-                    .attribute(CLASS(vec!["Sections".to_string()]))
-                    .build().unwrap())
-                .build().unwrap()
-        ),
-    ];
-    let prism_head = if include_prism {
-        "<link rel=\"stylesheet\" href=\"/prism.css\">"
-    } else {
-        ""
-    };
-    let prism_scripts = if include_prism {
-        "<script src=\"https://cdnjs.cloudflare.com/ajax/libs/prism/1.30.0/prism.min.js\"></script>\
-         <script src=\"https://cdnjs.cloudflare.com/ajax/libs/prism/1.30.0/components/prism-rust.min.js\"></script>\
-         <script src=\"https://cdnjs.cloudflare.com/ajax/libs/prism/1.30.0/components/prism-python.min.js\"></script>\
-         <script src=\"https://cdnjs.cloudflare.com/ajax/libs/prism/1.30.0/components/prism-javascript.min.js\"></script>"
-    } else {
-        ""
-    };
-    let og_tags = format!(
-        "<meta property=\"og:title\" content=\"{og_title}\">\
-         <meta property=\"og:description\" content=\"{og_description}\">\
-         <meta property=\"og:type\" content=\"{og_type}\">"
-    );
-    Html("<html>".to_string()
-        + "<head>
-          <title>Klamer.dev</title>
-          <link rel=\"icon\" type=\"image/png\" href=\"/favicon.png\">
-          <link rel=\"stylesheet\" href=\"/base.css\">"
-        + &og_tags
-        + prism_head
-        + "<script src=\"https://unpkg.com/htmx.org@1.9.10\" integrity=\"sha384-D1Kt99CQMDuVetoL1lrYwg5t+9QdHe7NLX/SoJYkXDFfX37iInKRy5xLSi8nO7UC\" crossorigin=\"anonymous\"></script>
-          </head>"
-        + "<body>"
-        + DivBuilder::default()
-        .element(DivBuilder::default()
-            .elements(top_nav)
-            .attributes(AttributesBuilder::default()
-                .attribute(CLASS(vec!["Container".to_string()]))
-                .build().unwrap())
-            .build().unwrap())
-        .element(DivBuilder::default()
-            .elements(content)
-            .attributes(AttributesBuilder::default()
-                .attribute(CLASS(vec!["Content".to_string()]))
-                .build().unwrap())
-            .build().unwrap())
-        .element(if include_footer { "<footer>©2026 Jack Klamer<p>Source: " } else { "" })
-        .element(if include_footer { Anchor("https://github.com/jklamer/klamer.dev".to_string(), "https://github.com/jklamer/klamer.dev") } else { Anchor("https://github.com/jklamer/klamer.dev".to_string(), "") })
-        .attributes(AttributesBuilder::default()
-            .attribute(CLASS(vec!["center".to_string()]))
-            .build().unwrap())
-        .build().unwrap()
-        .html_string().as_str()
-        + prism_scripts
-        + "</body>"
-        + "</html>")
+/// Rendered once per post, after that it's served from the page cache like everything else
+async fn blog_post_og_image(Path(post_name): Path<String>) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    match blog::find_post(&post_name) {
+        Some(post) => Ok((Extension(ContentHash(post.content_hash)), [(header::CONTENT_TYPE, HeaderValue::from_static("image/png"))], og_image::post_card(post).await)),
+        None => Err(four04().await),
+    }
 }
 
+async fn atom_feed() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, HeaderValue::from_static("application/atom+xml; charset=utf-8"))], feed::atom())
+}
+
+async fn rss_feed() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, HeaderValue::from_static("application/rss+xml; charset=utf-8"))], feed::rss())
+}
 
-async fn icon() -> &'static [u8] {
-    ICON
+async fn sitemap_xml() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, HeaderValue::from_static("application/xml; charset=utf-8"))], sitemap::sitemap(&listed_pages()))
 }
 
-async fn logo() -> &'static [u8] {
-    LOGO
+/// Every page the server can render, for warming the cache
+fn cached_paths() -> Vec<String> {
+    let posts = blog::posts().flat_map(|post| [format!("/blog/{}", post.slug), format!("/blog/{}/og.png", post.slug)]);
+    let feeds = ["/feed.xml", "/rss.xml", "/sitemap.xml", "/robots.txt"].map(str::to_string);
+    STATIC_PAGES.iter().map(|page| page.path.to_string()).chain(posts).chain(feeds).collect()
 }
 
-async fn base_css() -> &'static str {
-    BASE_CSS
+fn listed_pages() -> Vec<&'static str> {
+    STATIC_PAGES.iter().filter(|page| !page.unlisted).map(|page| page.path).collect()
 }
 
-async fn prism_css() -> &'static str {
-    PRISM_CSS
+async fn robots_txt() -> String {
+    sitemap::robots()
+}
+
+async fn annie_page() -> Html<String> {
+    PageMetaBuilder::default()
+        .title("Annie")
+        .description("She's the best")
+        .path("/annie")
+        .build().unwrap()
+        .page(vec!["She's the best".into()])
+}
+
+async fn four04() -> (StatusCode, Html<String>) {
+    (StatusCode::NOT_FOUND, PageMetaBuilder::default()
+        .title("Not found")
+        .description("klamer.dev")
+        .footer(false)
+        .build().unwrap()
+        .page(vec![Raw(FOUR04).into()]))
 }
 
 #[cfg(test)]
 mod test {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::io::Read;
+
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
     use super::*;
 
+    // counts allocations made by the current thread, so tests running in parallel don't interfere
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|a| a.set(a.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|a| a.set(a.get() + 1));
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (ALLOCATIONS.with(Cell::get) - before, result)
+    }
+
+    async fn get_page(uri: &str) -> (StatusCode, String) {
+        let response = app().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_page_is_404() {
+        let (status, body) = get_page("/not/a/page").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.contains(FOUR04));
+        assert!(body.contains(assets::BASE_CSS.fingerprinted_path));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_post_is_404() {
+        let (status, body) = get_page("/blog/not_a_post").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.contains(FOUR04));
+        assert!(body.contains(assets::BASE_CSS.fingerprinted_path));
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match() {
+        for uri in ["/blog", assets::BASE_CSS.fingerprinted_path] {
+            let response = app().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let etag = response.headers()[header::ETAG].clone();
+
+            let response = app().oneshot(Request::get(uri).header(header::IF_NONE_MATCH, etag.clone()).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", uri);
+            assert_eq!(response.headers()[header::ETAG], etag);
+            assert!(to_bytes(response.into_body(), usize::MAX).await.unwrap().is_empty());
+
+            let response = app().oneshot(Request::get(uri).header(header::IF_NONE_MATCH, "\"stale\"").body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_etags_come_from_content_hash() {
+        let post = blog::posts().next().unwrap();
+        for uri in [format!("/blog/{}", post.slug), format!("/blog/{}/og.png", post.slug)] {
+            let response = app().oneshot(Request::get(&uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert!(response.headers()[header::ETAG].to_str().unwrap().starts_with(&format!("\"{}", &post.content_hash[..16])), "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pages_are_compressed() {
+        let (_, identity) = get_page("/blog").await;
+        let response = app().oneshot(Request::get("/blog").header(header::ACCEPT_ENCODING, "gzip, br").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        let etag = response.headers()[header::ETAG].clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut decompressed = String::new();
+        brotli::Decompressor::new(&body[..], 4096).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, identity);
+
+        // the brotli ETag doesn't validate the identity body
+        let response = app().oneshot(Request::get("/blog").header(header::IF_NONE_MATCH, etag).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_pages_have_security_headers() {
+        for uri in ["/", "/blog", "/not/a/page", assets::BASE_CSS.fingerprinted_path] {
+            let response = SecurityHeaders::default().layer(app()).oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert!(response.headers().contains_key(header::CONTENT_SECURITY_POLICY), "{}", uri);
+            assert_eq!(response.headers()[header::X_CONTENT_TYPE_OPTIONS], "nosniff", "{}", uri);
+        }
+    }
+
     #[test]
-    fn test_post_name() {
-        assert_eq!(get_post_name("/somebullshit/postname"), "postname");
-        assert_eq!(get_post_name("/somebullshit/postname.html"), "postname");
-        assert_eq!(get_post_name("somebullshit/postname"), "postname");
-        assert_eq!(get_post_name("somebullshit/postname.html"), "postname");
-        assert_eq!(get_post_name("a/b/c/d/e/r/postname"), "postname");
-        assert_eq!(get_post_name("a/b/c/d/e/r/posthtml"), "posthtml");
+    fn test_page_escapes_metadata() {
+        let Html(body) = PageMetaBuilder::default()
+            .title("\"><script>alert(1)</script>")
+            .description("a & b")
+            .build().unwrap()
+            .page(vec!["<img src=x onerror=alert(1)>".into()]);
+        assert!(!body.contains("<script>alert(1)"));
+        assert!(!body.contains("<img src=x"));
+        assert!(body.contains("content=\"&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;\""));
+        assert!(body.contains("content=\"a &amp; b\""));
+    }
+
+    #[tokio::test]
+    async fn test_code_is_highlighted() {
+        let (_, body) = get_page("/blog/001_first_post").await;
+        assert!(body.contains("<pre class=\"language-rust\"><code class=\"language-rust\">"));
+        assert!(body.contains("<span class=\"token keyword\">fn</span>"));
+        assert!(body.contains(assets::PRISM_CSS.fingerprinted_path));
+        assert!(!body.contains("prism.min.js"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_post_og_images() {
+        let post = blog::posts().next().unwrap();
+        let (_, page) = get_page(&format!("/blog/{}", post.slug)).await;
+        assert!(page.contains(&format!("<meta property=\"og:image\" content=\"{}/blog/{}/og.png\">", SITE_URL, post.slug)));
+
+        let response = app().oneshot(Request::get(format!("/blog/{}/og.png", post.slug)).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert!(response.headers().contains_key(header::ETAG));
+        let png = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let info = png::Decoder::new(&png[..]).read_info().unwrap().info().clone();
+        assert_eq!((info.width, info.height), (og_image::WIDTH, og_image::HEIGHT));
+
+        assert_eq!(get_page("/blog/not_a_post/og.png").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_posts_have_social_metadata() {
+        for post in blog::posts() {
+            let (_, body) = get_page(&format!("/blog/{}", post.slug)).await;
+            assert!(!post.summary.is_empty(), "{}", post.slug);
+            assert!(body.contains(&format!("<meta property=\"og:url\" content=\"{}/blog/{}\">", SITE_URL, post.slug)));
+            assert!(body.contains(&format!("<meta property=\"article:published_time\" content=\"{}\">", post.published.rfc3339())));
+            for tag in post.tags {
+                assert!(body.contains(&format!("<meta property=\"article:tag\" content=\"{}\">", tag)), "{}", post.slug);
+            }
+        }
+    }
+
+    /// Every page, run through a spec compliant html5 parser, parses without errors
+    #[tokio::test]
+    async fn test_pages_are_valid_html() {
+        let post_pages = blog::posts().map(|post| format!("/blog/{}", post.slug));
+        for uri in STATIC_PAGES.iter().map(|p| p.path.to_string()).chain(post_pages).chain(["/not/a/page".to_string()]) {
+            let (_, body) = get_page(&uri).await;
+            let document = scraper::Html::parse_document(&body);
+            assert!(document.errors.is_empty(), "{}: {:?}", uri, document.errors);
+        }
+    }
+
+    /// Rendering writes into the one buffer, so once it's big enough it doesn't allocate at all.
+    /// What's left for a whole page is building the components. The bounds leave some room over the
+    /// 266 allocations the blog index (with two posts) and 208 a post take now, and keep them well
+    /// under the 663 and 415 from when every element concatenated its own strings.
+    #[test]
+    fn test_render_allocations() {
+        let post = blog::posts().next().unwrap();
+        let tree = html! {
+            <div>
+                <a href={format!("/blog/{}", post.slug)}>{post.title}</a>
+                {post_date(post)}
+                <ul class="post-list"><li>"a < b"</li><li>{Raw(post.content)}</li></ul>
+            </div>
+        };
+        let mut html = String::with_capacity(layout::PAGE_CAPACITY);
+        let (rendering, _) = allocations(|| tree.render_to(&mut html).unwrap());
+        assert_eq!(rendering, 0);
+
+        let (blog_index, _) = allocations(|| futures::executor::block_on(blog_page()));
+        let (post_page, _) = allocations(|| futures::executor::block_on(blog_post(Path(post.slug.to_string()))));
+        // the page around the list, then each post's entry in it
+        assert!(blog_index <= 220 + 40 * blog::posts().count(), "blog index: {} allocations", blog_index);
+        assert!(post_page <= 250, "post: {} allocations", post_page);
+    }
+
+    #[tokio::test]
+    async fn test_known_pages_are_ok() {
+        for page in STATIC_PAGES {
+            assert_eq!(get_page(page.path).await.0, StatusCode::OK, "{}", page.path);
+        }
+        for post in blog::posts() {
+            assert_eq!(get_page(&format!("/blog/{}", post.slug)).await.0, StatusCode::OK, "{}", post.slug);
+        }
+    }
+    #[tokio::test]
+    async fn test_cached_paths_render() {
+        let paths = cached_paths();
+        assert!(paths.contains(&"/blog/archive".to_string()) && paths.contains(&"/sitemap.xml".to_string()));
+        for path in paths {
+            let response = app().oneshot(Request::get(&path).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_lists_routed_pages() {
+        let (_, sitemap) = get_page("/sitemap.xml").await;
+        for page in STATIC_PAGES {
+            let entry = format!("<loc>{}{}</loc>", SITE_URL, page.path);
+            assert_eq!(sitemap.contains(&entry), !page.unlisted, "{}", page.path);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use axum::body::Bytes;
+use tokio::sync::OnceCell;
+
+use crate::assets;
+use crate::blog::BlogPost;
+
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+const MARGIN: u32 = 80;
+const BACKGROUND: [u8; 3] = [0xed, 0xe4, 0xca];
+const TITLE_COLOR: [u8; 3] = [40, 42, 84];
+const DATE_COLOR: [u8; 3] = [0x33, 0x33, 0x33];
+const TITLE_SIZE: f32 = 64.0;
+const TITLE_LINE_HEIGHT: f32 = 80.0;
+const TITLE_MAX_LINES: usize = 3;
+const DATE_SIZE: f32 = 36.0;
+
+// monospace like the site's Space Mono, see assets/fonts/LICENSE-DejaVu
+static FONT: LazyLock<FontRef<'static>> = LazyLock::new(|| {
+    FontRef::try_from_slice(include_bytes!("../assets/fonts/DejaVuSansMono-Bold.ttf")).expect("the font compiled in is valid")
+});
+
+// set by whichever request gets there first, the rest wait on it
+type Card = Arc<OnceCell<Bytes>>;
+
+// keyed by slug, and the posts are compiled in, so there's never more than one per post
+static CARDS: LazyLock<Mutex<HashMap<&'static str, Card>>> = LazyLock::new(Default::default);
+
+/// `render` for a post, once however many requests for it arrive before it's done, and on the
+/// blocking pool since drawing and compressing the PNG takes a while
+pub async fn post_card(post: &BlogPost) -> Bytes {
+    let card = CARDS.lock().unwrap().entry(post.slug).or_default().clone();
+    card.get_or_init(|| {
+        let (title, date) = (post.title, format!("{} {}, {}", post.published.month_name(), post.published.day, post.published.year));
+        async move {
+            tokio::task::spawn_blocking(move || Bytes::from(render(title, &date))).await.expect("rendering doesn't panic")
+        }
+    }).await.clone()
+}
+
+/// The link preview for a post: the logo, its title and date on the site's background, as a PNG
+pub fn render(title: &str, date: &str) -> Vec<u8> {
+    let mut canvas = Canvas::new(BACKGROUND);
+    let logo = Rgba::decode(assets::LOGO.bytes);
+    canvas.draw_half_size(&logo, MARGIN, 60);
+
+    let title_top = 60 + logo.height / 2 + 40;
+    for (i, line) in wrap(title, TITLE_SIZE, (WIDTH - 2 * MARGIN) as f32, TITLE_MAX_LINES).iter().enumerate() {
+        let baseline = title_top as f32 + TITLE_SIZE + i as f32 * TITLE_LINE_HEIGHT;
+        canvas.draw_text(line, TITLE_SIZE, MARGIN as f32, baseline, TITLE_COLOR);
+    }
+    canvas.draw_text(date, DATE_SIZE, MARGIN as f32, (HEIGHT - 70) as f32, DATE_COLOR);
+    canvas.fill(0, HEIGHT - 16, WIDTH, 16, TITLE_COLOR);
+    canvas.encode()
+}
+
+fn text_width(text: &str, size: f32) -> f32 {
+    let font = FONT.as_scaled(PxScale::from(size));
+    text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum()
+}
+
+/// Greedy word wrap, the last line ends in an ellipsis when the text doesn't fit. A word wider
+/// than a whole line is broken wherever it runs out of room.
+fn wrap(text: &str, size: f32, max_width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+        if text_width(&candidate, size) <= max_width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            if !line.is_empty() && text_width(&format!("{}{}", line, c), size) > max_width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.last_mut().unwrap();
+        while !last.is_empty() && text_width(&format!("{}…", last), size) > max_width {
+            last.pop();
+        }
+        *last = format!("{}…", last.trim_end());
+    }
+    lines
+}
+
+/// Decoded image with straight alpha
+struct Rgba {
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Rgba {
+    fn decode(png_bytes: &[u8]) -> Self {
+        let mut decoder = png::Decoder::new(png_bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().expect("the logo compiled in is a valid png");
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).expect("the logo compiled in is a valid png");
+        let buffer = &buffer[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+            png::ColorType::Rgb => buffer.chunks_exact(3).map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            // palettes are expanded by normalize_to_color8
+            png::ColorType::Grayscale | png::ColorType::Indexed => buffer.iter().map(|&g| [g, g, g, 255]).collect(),
+        };
+        Rgba { width: info.width, height: info.height, pixels }
+    }
+}
+
+/// Opaque RGB pixels the card is drawn on
+struct Canvas {
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Canvas {
+    fn new(background: [u8; 3]) -> Self {
+        Canvas { pixels: vec![background; (WIDTH * HEIGHT) as usize] }
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 3], alpha: f32) {
+        if x < 0 || y < 0 || x >= WIDTH as i64 || y >= HEIGHT as i64 {
+            return;
+        }
+        let pixel = &mut self.pixels[(y as u32 * WIDTH + x as u32) as usize];
+        let alpha = alpha.clamp(0.0, 1.0);
+        for (channel, new) in pixel.iter_mut().zip(color) {
+            *channel = (*channel as f32 * (1.0 - alpha) + new as f32 * alpha).round() as u8;
+        }
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.blend(x as i64, y as i64, color, 1.0);
+            }
+        }
+    }
+
+    /// Averages each 2x2 block, weighted by alpha so transparent pixels don't darken the edges
+    fn draw_half_size(&mut self, image: &Rgba, left: u32, top: u32) {
+        for y in 0..image.height / 2 {
+            for x in 0..image.width / 2 {
+                let block = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| image.pixels[((2 * y + dy) * image.width + 2 * x + dx) as usize]);
+                let alpha: f32 = block.iter().map(|p| p[3] as f32).sum();
+                if alpha == 0.0 {
+                    continue;
+                }
+                let color = [0, 1, 2].map(|c| (block.iter().map(|p| p[c] as f32 * p[3] as f32).sum::<f32>() / alpha).round() as u8);
+                self.blend((left + x) as i64, (top + y) as i64, color, alpha / (4.0 * 255.0));
+            }
+        }
+    }
+
+    fn draw_text(&mut self, text: &str, size: f32, left: f32, baseline: f32, color: [u8; 3]) {
+        let scale = PxScale::from(size);
+        let font = FONT.as_scaled(scale);
+        let mut caret = left;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(outline) = FONT.outline_glyph(id.with_scale_and_position(scale, point(caret, baseline))) {
+                let bounds = outline.px_bounds();
+                outline.draw(|x, y, coverage| self.blend(bounds.min.x as i64 + x as i64, bounds.min.y as i64 + y as i64, color, coverage));
+            }
+            caret += font.h_advance(id);
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut png_bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut png_bytes, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::Best);
+        let mut writer = encoder.write_header().expect("writing to a Vec can't fail");
+        writer.write_image_data(self.pixels.as_flattened()).expect("writing to a Vec can't fail");
+        writer.finish().expect("writing to a Vec can't fail");
+        png_bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let card = render("Jersey numbers", "May 1, 2025");
+        let decoded = Rgba::decode(&card);
+        assert_eq!((decoded.width, decoded.height), (WIDTH, HEIGHT));
+        let pixel = |x: u32, y: u32| decoded.pixels[(y * WIDTH + x) as usize];
+        assert_eq!(pixel(5, 5), [BACKGROUND[0], BACKGROUND[1], BACKGROUND[2], 255]);
+        assert_eq!(pixel(WIDTH / 2, HEIGHT - 1), [TITLE_COLOR[0], TITLE_COLOR[1], TITLE_COLOR[2], 255]);
+        // something got drawn where the title and logo go
+        assert!(decoded.pixels[(MARGIN * WIDTH) as usize..(HEIGHT / 2 * WIDTH) as usize].iter().any(|p| p[..3] != BACKGROUND));
+    }
+
+    #[tokio::test]
+    async fn test_post_card_renders_once() {
+        let post = crate::blog::posts().next().unwrap();
+        let (first, second) = tokio::join!(post_card(post), post_card(post));
+        assert_eq!(first.as_ptr(), second.as_ptr());
+        assert_eq!(first, render(post.title, &format!("{} {}, {}", post.published.month_name(), post.published.day, post.published.year)));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let width = text_width("abcdefghij", TITLE_SIZE);
+        assert_eq!(wrap("short", TITLE_SIZE, width, 3), vec!["short"]);
+        assert_eq!(wrap("abcd efgh ijkl", TITLE_SIZE, width, 3), vec!["abcd efgh", "ijkl"]);
+        let truncated = wrap("aaaa bbbb cccc dddd eeee ffff gggg", TITLE_SIZE, width, 2);
+        assert_eq!(truncated.len(), 2);
+        assert!(truncated[1].ends_with('…'));
+        assert!(text_width(&truncated[1], TITLE_SIZE) <= width);
+
+        let long = wrap("ab abcdefghijklmnopqrstuvwxy", TITLE_SIZE, width, 3);
+        assert_eq!(long, vec!["ab", "abcdefghij", "klmnopqrs…"]);
+        assert!(long.iter().all(|line| text_width(line, TITLE_SIZE) <= width));
+        assert_eq!(wrap("abcdefghijklm", TITLE_SIZE, width, 3), vec!["abcdefghij", "klm"]);
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, HeaderValue};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::Router;
+use derive_builder::Builder;
+
+/// Headers added to every response. The CSP source lists cover exactly what pages load, there are
+/// no inline scripts or `<style>` elements, only `style` attributes. base.css imports its fonts from Google Fonts, a stylesheet on
+/// fonts.googleapis.com that loads the font files from fonts.gstatic.com.
+#[derive(Clone, Builder)]
+#[builder(pattern = "owned")]
+pub struct SecurityHeaders {
+    /// `Strict-Transport-Security` max-age, only for responses served over TLS
+    #[builder(setter(strip_option), default)]
+    hsts: Option<Duration>,
+    /// Extends HSTS to every subdomain, which have to be served over TLS as well then
+    #[builder(default)]
+    hsts_include_subdomains: bool,
+    #[builder(setter(each(name = "script_src", into)), default = "vec![\"'self'\".to_string()]")]
+    script_srcs: Vec<String>,
+    #[builder(setter(each(name = "style_src", into)), default = "vec![\"'self'\".to_string(), \"https://fonts.googleapis.com\".to_string()]")]
+    style_srcs: Vec<String>,
+    #[builder(setter(each(name = "font_src", into)), default = "vec![\"https://fonts.gstatic.com\".to_string()]")]
+    font_srcs: Vec<String>,
+    #[builder(setter(each(name = "img_src", into)), default = "vec![\"'self'\".to_string()]")]
+    img_srcs: Vec<String>,
+    #[builder(setter(into), default = "\"strict-origin-when-cross-origin\".to_string()")]
+    referrer_policy: String,
+    #[builder(setter(into), default = "\"camera=(), microphone=(), geolocation=(), payment=(), usb=(), browsing-topics=()\".to_string()")]
+    permissions_policy: String,
+}
+
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeadersBuilder::default().build().unwrap()
+    }
+}
+
+impl SecurityHeaders {
+    fn content_security_policy(&self) -> String {
+        format!(
+            "default-src 'self'; script-src {}; style-src {}; style-src-attr 'unsafe-inline'; font-src {}; img-src {}; connect-src 'self'; \
+             object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'",
+            self.script_srcs.join(" "),
+            self.style_srcs.join(" "),
+            self.font_srcs.join(" "),
+            self.img_srcs.join(" "),
+        )
+    }
+
+    /// Wraps every route in the router, including the fallback
+    pub fn layer(self, router: Router) -> Router {
+        router.layer(middleware::from_fn_with_state(Arc::new(self), security_headers))
+    }
+}
+
+async fn security_headers(State(config): State<Arc<SecurityHeaders>>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_str(&config.content_security_policy()).unwrap());
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(header::REFERRER_POLICY, HeaderValue::from_str(&config.referrer_policy).unwrap());
+    headers.insert(HeaderName::from_static("permissions-policy"), HeaderValue::from_str(&config.permissions_policy).unwrap());
+    if let Some(max_age) = config.hsts {
+        let subdomains = if config.hsts_include_subdomains { "; includeSubDomains" } else { "" };
+        headers.insert(header::STRICT_TRANSPORT_SECURITY, HeaderValue::from_str(&format!("max-age={}{}", max_age.as_secs(), subdomains)).unwrap());
+    }
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::assets;
+
+    fn router(config: SecurityHeaders) -> Router {
+        config.layer(Router::new().route("/", get(|| async { "ok" })))
+    }
+
+    #[tokio::test]
+    async fn test_security_headers() {
+        let response = router(SecurityHeaders::default()).oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        let headers = response.headers().clone();
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::REFERRER_POLICY], "strict-origin-when-cross-origin");
+        assert!(headers.contains_key("permissions-policy"));
+        assert!(!headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+        let csp = headers[header::CONTENT_SECURITY_POLICY].to_str().unwrap();
+        assert!(csp.contains("script-src 'self';"));
+        assert!(csp.contains("style-src 'self' https://fonts.googleapis.com; style-src-attr 'unsafe-inline';"));
+        assert!(!csp.contains("nonce"));
+    }
+
+    #[test]
+    fn test_csp_allows_stylesheet_imports() {
+        let csp = SecurityHeaders::default().content_security_policy();
+        let sources = |directive: &str| csp.split("; ")
+            .find_map(|d| d.strip_prefix(directive)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("no {} in {}", directive, csp))
+            .split(' ').map(str::to_string).collect::<Vec<_>>();
+        for asset in [assets::BASE_CSS, assets::PRISM_CSS] {
+            let css = std::str::from_utf8(asset.bytes).unwrap();
+            for import in css.split("@import url(").skip(1) {
+                let url = import.trim_start_matches(['\'', '"']);
+                if !url.starts_with("https://") {
+                    continue;
+                }
+                let origin = url.splitn(4, '/').take(3).collect::<Vec<_>>().join("/");
+                assert!(sources("style-src").contains(&origin), "{} imports {}", asset.path, url);
+                // the Google Fonts stylesheet loads the fonts themselves from another host
+                if origin == "https://fonts.googleapis.com" {
+                    assert!(sources("font-src").contains(&"https://fonts.gstatic.com".to_string()));
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hsts() {
+        let config = SecurityHeadersBuilder::default().hsts(Duration::from_secs(63072000)).build().unwrap();
+        let response = router(config).oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[header::STRICT_TRANSPORT_SECURITY], "max-age=63072000");
+
+        let config = SecurityHeadersBuilder::default().hsts(Duration::from_secs(63072000)).hsts_include_subdomains(true).build().unwrap();
+        let response = router(config).oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[header::STRICT_TRANSPORT_SECURITY], "max-age=63072000; includeSubDomains");
+    }
+}
@@ -0,0 +1,45 @@
+use crate::blog;
+use crate::feed::escape_xml;
+use crate::SITE_URL;
+
+/// `sitemap.xml` covering the given static pages and every published post. The blog index pages
+/// change whenever a post is published, so they get the newest post's date as `lastmod`.
+pub fn sitemap(static_pages: &[&str]) -> String {
+    let newest = blog::posts().next().map(|p| p.published.to_string());
+    let mut sitemap = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">".to_string();
+    for path in static_pages {
+        let lastmod = if path.starts_with("/blog") { newest.as_deref() } else { None };
+        sitemap += &url_entry(&format!("{}{}", SITE_URL, path), lastmod);
+    }
+    for post in blog::posts() {
+        sitemap += &url_entry(&format!("{}/blog/{}", SITE_URL, post.slug), Some(&post.published.to_string()));
+    }
+    sitemap + "</urlset>"
+}
+
+fn url_entry(location: &str, lastmod: Option<&str>) -> String {
+    match lastmod {
+        Some(lastmod) => format!("<url><loc>{}</loc><lastmod>{}</lastmod></url>", escape_xml(location), lastmod),
+        None => format!("<url><loc>{}</loc></url>", escape_xml(location)),
+    }
+}
+
+pub fn robots() -> String {
+    format!("User-agent: *\nAllow: /\n\nSitemap: {}/sitemap.xml\n", SITE_URL)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sitemap() {
+        let sitemap = sitemap(&["/", "/blog"]);
+        assert!(sitemap.contains("<url><loc>https://klamer.dev/</loc></url>"));
+        for post in blog::posts() {
+            assert!(sitemap.contains(&format!("<url><loc>{}/blog/{}</loc><lastmod>{}</lastmod></url>", SITE_URL, post.slug, post.published)));
+        }
+        assert!(robots().contains("Sitemap: https://klamer.dev/sitemap.xml"));
+    }
+}
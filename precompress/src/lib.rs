@@ -0,0 +1,30 @@
+//! The gzip and brotli settings shared by the build time (`static_asset!`) and runtime (the page
+//! cache) compression, both at their best since everything is compressed once and served many times.
+
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+// a compressed variant is only worth keeping if it's at most this fraction of the original
+const MIN_COMPRESSION_RATIO: f64 = 0.9;
+
+pub fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::best());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+pub fn brotli(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = vec![];
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        encoder.write_all(bytes).unwrap();
+    }
+    compressed
+}
+
+/// Whether sending `compressed` instead of `original` saves enough to bother
+pub fn pays_off(original: &[u8], compressed: &[u8]) -> bool {
+    (compressed.len() as f64) <= original.len() as f64 * MIN_COMPRESSION_RATIO
+}
@@ -41,7 +41,7 @@ impl AcmeS3Cache {
         let mut ctx = Sha256::default();
         for domain in domains {
             ctx.update(domain.as_bytes());
-            ctx.update(&[0])
+            ctx.update([0])
         }
         ctx.update(directory_url.as_ref().as_bytes());
         let hash = BASE64_URL_SAFE_NO_PAD.encode(ctx.finalize());
@@ -62,26 +62,26 @@ impl CertCache for AcmeS3Cache {
     type EC = MyErrors;
 
     async fn load_cert(&self, domains: &[String], directory_url: &str) -> Result<Option<Vec<u8>>, Self::EC> {
-        let file_name = Self::cached_cert_file_name(&domains, directory_url);
+        let file_name = Self::cached_cert_file_name(domains, directory_url);
         let get_object_output = Self::get_client().await
             .get_object()
             .bucket(&self.bucket)
-            .key(&format!("{}/{}", &self.prefix, file_name))
+            .key(format!("{}/{}", &self.prefix, file_name))
             .send().await
             .map_err(MyErrors::GetObjectError)?;
 
         get_object_output.body.collect().await.map(|aggregated_bytes| aggregated_bytes.to_vec())
-            .map(|bytes| if bytes.len() == 0 { None } else { Some(bytes) })
+            .map(|bytes| if bytes.is_empty() { None } else { Some(bytes) })
             .map_err(MyErrors::ByteStreamError)
     }
 
     //note for posterity, written mostly by co-pilot
     async fn store_cert(&self, domains: &[String], directory_url: &str, cert: &[u8]) -> Result<(), Self::EC> {
-        let file_name = Self::cached_cert_file_name(&domains, directory_url);
+        let file_name = Self::cached_cert_file_name(domains, directory_url);
         Self::use_client(|client| {
             client.put_object()
                 .bucket(&self.bucket)
-                .key(&format!("{}/{}", &self.prefix, file_name))
+                .key(format!("{}/{}", &self.prefix, file_name))
                 .body(ByteStream::from(Vec::from(cert)))
                 .send()
         }).await.map(|_| ())